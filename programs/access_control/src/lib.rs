@@ -1,7 +1,18 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
 
 declare_id!("BbuwPUfFjenFAK4Y2jYfTpf7oo8uWDafFfJeh7FDApSz");
 
+pub const MAX_ROLES: usize = 5;
+pub const MAX_MEMBERS_PER_ROLE: usize = 8;
+pub const MAX_MULTISIG_SIGNERS: usize = 10;
+/// 提案建立後可核准與執行的期限
+pub const PROPOSAL_LIFETIME: i64 = 7 * 86400;
+
+pub const ASSET_MANAGER_PROGRAM_ID: Pubkey = solana_program::pubkey!("HiudtiK3QwUEiRWU3WB7h1azKvu7kpwbZyU1qBvaUUD6");
+
 #[program]
 pub mod access_control {
     use super::*;
@@ -9,18 +20,19 @@ pub mod access_control {
     pub fn initialize(ctx: Context<Initialize>, bump: u8) -> Result<()> {
         let access_control = &mut ctx.accounts.access_control;
         access_control.admin = ctx.accounts.admin.key();
+        access_control.pending_admin = None;
         access_control.is_paused = false;
-        access_control.permissions = Vec::new();
+        access_control.roles = Vec::new();
         
         let pda_key = access_control.key();
         let admin_key = ctx.accounts.admin.key();
         let is_paused = access_control.is_paused;
-        let permissions_len = access_control.permissions.len();
+        let roles_len = access_control.roles.len();
         
         msg!("AccessControl PDA initialized: {:?}", pda_key);
         msg!("Admin: {:?}", admin_key);
         msg!("Is paused: {}", is_paused);
-        msg!("Permissions length: {}", permissions_len);
+        msg!("Roles length: {}", roles_len);
         msg!("Bump: {}", bump);
         
         Ok(())
@@ -28,12 +40,12 @@ pub mod access_control {
 
     pub fn emergency_stop(ctx: Context<EmergencyStop>) -> Result<()> {
         let access_control = &mut ctx.accounts.access_control;
-        require!(ctx.accounts.admin.key() == access_control.admin, AccessControlError::Unauthorized);
+        require!(access_control.can_pause(&ctx.accounts.admin.key()), AccessControlError::Unauthorized);
 
         require!(!access_control.is_paused, AccessControlError::AlreadyPaused);
 
         access_control.is_paused = true;
-        msg!("Emergency stop activated by admin: {:?}", ctx.accounts.admin.key());
+        msg!("Emergency stop activated by: {:?}", ctx.accounts.admin.key());
         Ok(())
     }
 
@@ -48,6 +60,296 @@ pub mod access_control {
         Ok(())
     }
 
+    pub fn grant_role(ctx: Context<SetPermissions>, role: Role, account: Pubkey) -> Result<()> {
+        let access_control = &mut ctx.accounts.access_control;
+        require!(
+            access_control.has_role(Role::Admin, &ctx.accounts.admin.key()),
+            AccessControlError::Unauthorized
+        );
+        access_control.grant(role, account)?;
+
+        emit!(RoleGrantedEvent {
+            role,
+            account,
+            granted_by: ctx.accounts.admin.key(),
+        });
+
+        msg!("Role {:?} granted to {:?}", role, account);
+        Ok(())
+    }
+
+    pub fn revoke_role(ctx: Context<SetPermissions>, role: Role, account: Pubkey) -> Result<()> {
+        let access_control = &mut ctx.accounts.access_control;
+        require!(
+            access_control.has_role(Role::Admin, &ctx.accounts.admin.key()),
+            AccessControlError::Unauthorized
+        );
+        access_control.revoke(role, account)?;
+
+        emit!(RoleRevokedEvent {
+            role,
+            account,
+            revoked_by: ctx.accounts.admin.key(),
+        });
+
+        msg!("Role {:?} revoked from {:?}", role, account);
+        Ok(())
+    }
+
+    pub fn has_role(ctx: Context<HasRole>, role: Role, account: Pubkey) -> Result<bool> {
+        let has_role = ctx.accounts.access_control.has_role(role, &account);
+        msg!("Account {:?} has role {:?}: {}", account, role, has_role);
+        Ok(has_role)
+    }
+
+    pub fn propose_admin(ctx: Context<TransferAdmin>, new_admin: Pubkey) -> Result<()> {
+        let access_control = &mut ctx.accounts.access_control;
+        require!(ctx.accounts.admin.key() == access_control.admin, AccessControlError::Unauthorized);
+        access_control.propose_admin(new_admin)?;
+
+        emit!(AdminProposedEvent {
+            current_admin: access_control.admin,
+            pending_admin: new_admin,
+        });
+
+        msg!("Admin handover proposed to: {:?}", new_admin);
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<TransferAdmin>) -> Result<()> {
+        let access_control = &mut ctx.accounts.access_control;
+        let pending_admin = access_control.pending_admin.ok_or(AccessControlError::NoPendingAdmin)?;
+        require!(ctx.accounts.admin.key() == pending_admin, AccessControlError::Unauthorized);
+
+        let old_admin = access_control.admin;
+        access_control.admin = pending_admin;
+        access_control.pending_admin = None;
+
+        emit!(AdminTransferredEvent {
+            old_admin,
+            new_admin: pending_admin,
+        });
+
+        msg!("Admin transferred from {:?} to {:?}", old_admin, pending_admin);
+        Ok(())
+    }
+
+    pub fn create_multisig(ctx: Context<CreateMultisig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        require!(ctx.accounts.admin.key() == ctx.accounts.access_control.admin, AccessControlError::Unauthorized);
+        validate_multisig(&signers, threshold)?;
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.signers = signers;
+        multisig.threshold = threshold;
+        multisig.proposal_count = 0;
+        multisig.generation = 0;
+        multisig.bump = ctx.bumps.multisig;
+
+        emit!(MultisigUpdatedEvent {
+            signers: multisig.signers.clone(),
+            threshold,
+        });
+
+        // 建立時即將兩個程式的管理權交給 multisig，不留單一金鑰仍可直接呼叫管理指令的空窗
+        let multisig_key = multisig.key();
+        let access_control = &mut ctx.accounts.access_control;
+        let old_admin = access_control.admin;
+        access_control.admin = multisig_key;
+        access_control.pending_admin = None;
+
+        emit!(AdminTransferredEvent {
+            old_admin,
+            new_admin: multisig_key,
+        });
+
+        let ix = Instruction {
+            program_id: ASSET_MANAGER_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(ctx.accounts.asset_manager_state.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.admin.key(), true),
+            ],
+            data: asset_manager_ix_data("transfer_authority", multisig_key.try_to_vec()?),
+        };
+        invoke(
+            &ix,
+            &[
+                ctx.accounts.asset_manager_state.to_account_info(),
+                ctx.accounts.admin.to_account_info(),
+                ctx.accounts.asset_manager_program.to_account_info(),
+            ],
+        )?;
+
+        msg!("Multisig created with {} signers, threshold {}", ctx.accounts.multisig.signers.len(), threshold);
+        Ok(())
+    }
+
+    pub fn create_proposal(ctx: Context<CreateProposal>, action: ProposalAction) -> Result<()> {
+        let multisig = &mut ctx.accounts.multisig;
+        let proposer = ctx.accounts.proposer.key();
+        require!(multisig.signers.contains(&proposer), AccessControlError::NotASigner);
+        if let ProposalAction::SetMultisig { signers, threshold } = &action {
+            validate_multisig(signers, *threshold)?;
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.id = multisig.proposal_count;
+        proposal.proposer = proposer;
+        proposal.action = action.clone();
+        proposal.approvals = vec![proposer];
+        proposal.executed = false;
+        proposal.created_at = now;
+        proposal.expires_at = now
+            .checked_add(PROPOSAL_LIFETIME)
+            .ok_or(AccessControlError::CalculationError)?;
+        proposal.generation = multisig.generation;
+
+        multisig.proposal_count = multisig.proposal_count
+            .checked_add(1)
+            .ok_or(AccessControlError::CalculationError)?;
+
+        emit!(ProposalCreatedEvent {
+            proposal_id: proposal.id,
+            proposer,
+            action,
+        });
+
+        msg!("Proposal {} created by {:?}", proposal.id, proposer);
+        Ok(())
+    }
+
+    pub fn approve(ctx: Context<Approve>) -> Result<()> {
+        let approver = ctx.accounts.approver.key();
+        require!(ctx.accounts.multisig.signers.contains(&approver), AccessControlError::NotASigner);
+
+        let proposal = &mut ctx.accounts.proposal;
+        require!(!proposal.executed, AccessControlError::ProposalAlreadyExecuted);
+        proposal.check_open(&ctx.accounts.multisig)?;
+        require!(!proposal.approvals.contains(&approver), AccessControlError::AlreadyApproved);
+        proposal.approvals.push(approver);
+
+        emit!(ProposalApprovedEvent {
+            proposal_id: proposal.id,
+            approver,
+            approvals: proposal.approvals.len() as u8,
+        });
+
+        msg!("Proposal {} approved by {:?}", proposal.id, approver);
+        Ok(())
+    }
+
+    pub fn execute<'info>(ctx: Context<'_, '_, 'info, 'info, Execute<'info>>) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        require!(
+            multisig.signers.contains(&ctx.accounts.executor.key()),
+            AccessControlError::NotASigner
+        );
+        require!(!ctx.accounts.proposal.executed, AccessControlError::ProposalAlreadyExecuted);
+        ctx.accounts.proposal.check_open(multisig)?;
+
+        // 僅計算仍在目前簽署者名單中的核准
+        let approvals = ctx.accounts.proposal.approvals
+            .iter()
+            .filter(|a| multisig.signers.contains(a))
+            .count();
+        require!(approvals >= multisig.threshold as usize, AccessControlError::ThresholdNotMet);
+
+        ctx.accounts.proposal.executed = true;
+        let action = ctx.accounts.proposal.action.clone();
+        let multisig_key = ctx.accounts.multisig.key();
+
+        // 除了接手管理權本身，其餘 access_control 動作都要求 multisig 已是管理員
+        if action.requires_admin() {
+            require!(
+                ctx.accounts.access_control.has_role(Role::Admin, &multisig_key),
+                AccessControlError::Unauthorized
+            );
+        }
+
+        match &action {
+            ProposalAction::EmergencyStop => {
+                let access_control = &mut ctx.accounts.access_control;
+                require!(!access_control.is_paused, AccessControlError::AlreadyPaused);
+                access_control.is_paused = true;
+            }
+            ProposalAction::Resume => {
+                let access_control = &mut ctx.accounts.access_control;
+                require!(access_control.is_paused, AccessControlError::NotPaused);
+                access_control.is_paused = false;
+            }
+            ProposalAction::AcceptAdmin => {
+                let access_control = &mut ctx.accounts.access_control;
+                require!(
+                    access_control.pending_admin == Some(multisig_key),
+                    AccessControlError::NoPendingAdmin
+                );
+                let old_admin = access_control.admin;
+                access_control.admin = multisig_key;
+                access_control.pending_admin = None;
+                emit!(AdminTransferredEvent {
+                    old_admin,
+                    new_admin: multisig_key,
+                });
+            }
+            ProposalAction::CloseAccount { recipient } => {
+                let recipient_info = ctx.remaining_accounts
+                    .first()
+                    .ok_or(AccessControlError::MissingTargetAccounts)?;
+                require_keys_eq!(recipient_info.key(), *recipient, AccessControlError::MissingTargetAccounts);
+                ctx.accounts.access_control.close(recipient_info.clone())?;
+            }
+            ProposalAction::GrantRole { role, account } => {
+                ctx.accounts.access_control.grant(*role, *account)?;
+                emit!(RoleGrantedEvent {
+                    role: *role,
+                    account: *account,
+                    granted_by: multisig_key,
+                });
+            }
+            ProposalAction::RevokeRole { role, account } => {
+                ctx.accounts.access_control.revoke(*role, *account)?;
+                emit!(RoleRevokedEvent {
+                    role: *role,
+                    account: *account,
+                    revoked_by: multisig_key,
+                });
+            }
+            ProposalAction::ProposeAdmin { new_admin } => {
+                ctx.accounts.access_control.propose_admin(*new_admin)?;
+                emit!(AdminProposedEvent {
+                    current_admin: multisig_key,
+                    pending_admin: *new_admin,
+                });
+            }
+            ProposalAction::AssetManager { call } => {
+                invoke_asset_manager(call, &ctx.accounts.multisig, ctx.remaining_accounts)?;
+            }
+            ProposalAction::SetMultisig { signers, threshold } => {
+                validate_multisig(signers, *threshold)?;
+                let multisig = &mut ctx.accounts.multisig;
+                multisig.signers = signers.clone();
+                multisig.threshold = *threshold;
+                // 換屆後舊簽署者的核准一律作廢，尚未執行的提案需重新提出
+                multisig.generation = multisig.generation
+                    .checked_add(1)
+                    .ok_or(AccessControlError::CalculationError)?;
+                emit!(MultisigUpdatedEvent {
+                    signers: signers.clone(),
+                    threshold: *threshold,
+                });
+            }
+        }
+
+        emit!(ProposalExecutedEvent {
+            proposal_id: ctx.accounts.proposal.id,
+            executor: ctx.accounts.executor.key(),
+            action,
+        });
+
+        msg!("Proposal {} executed", ctx.accounts.proposal.id);
+        Ok(())
+    }
+
     pub fn close_account(ctx: Context<CloseAccount>) -> Result<()> {
         require!(ctx.accounts.admin.key() == ctx.accounts.access_control.admin, AccessControlError::Unauthorized);
         
@@ -61,8 +363,8 @@ pub struct Initialize<'info> {
     #[account(
         init,
         payer = admin,
-        space = 8 + 32 + 1 + 4 + (32 + 1) * 20 + 128,
-        seeds = [b"access_control", admin.key().as_ref()],
+        space = AccessControl::SPACE,
+        seeds = [b"access_control"],
         bump
     )]
     pub access_control: Account<'info, AccessControl>,
@@ -75,18 +377,38 @@ pub struct Initialize<'info> {
 pub struct SetPermissions<'info> {
     #[account(
         mut,
-        seeds = [b"access_control", admin.key().as_ref()],
+        seeds = [b"access_control"],
         bump
     )]
     pub access_control: Account<'info, AccessControl>,
     pub admin: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct TransferAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"access_control"],
+        bump
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct HasRole<'info> {
+    #[account(
+        seeds = [b"access_control"],
+        bump
+    )]
+    pub access_control: Account<'info, AccessControl>,
+}
+
 #[derive(Accounts)]
 pub struct EmergencyStop<'info> {
     #[account(
         mut,
-        seeds = [b"access_control", admin.key().as_ref()],
+        seeds = [b"access_control"],
         bump
     )]
     pub access_control: Account<'info, AccessControl>,
@@ -98,7 +420,7 @@ pub struct CloseAccount<'info> {
     #[account(
         mut,
         close = admin,
-        seeds = [b"access_control", admin.key().as_ref()],
+        seeds = [b"access_control"],
         bump
     )]
     pub access_control: Account<'info, AccessControl>,
@@ -106,11 +428,305 @@ pub struct CloseAccount<'info> {
     pub admin: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct CreateMultisig<'info> {
+    #[account(
+        mut,
+        seeds = [b"access_control"],
+        bump
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(
+        init,
+        payer = admin,
+        space = Multisig::SPACE,
+        seeds = [b"multisig"],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    /// CHECK: asset_manager 的 ProgramState，由 asset_manager 驗證 admin 為其目前的 authority
+    #[account(mut)]
+    pub asset_manager_state: AccountInfo<'info>,
+    /// CHECK: Only used as the CPI target
+    #[account(address = ASSET_MANAGER_PROGRAM_ID @ AccessControlError::InvalidTargetProgram)]
+    pub asset_manager_program: AccountInfo<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        init,
+        payer = proposer,
+        space = Proposal::SPACE,
+        seeds = [b"proposal", multisig.proposal_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Approve<'info> {
+    #[account(
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Execute<'info> {
+    #[account(
+        mut,
+        seeds = [b"access_control"],
+        bump
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(
+        mut,
+        seeds = [b"multisig"],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+    pub executor: Signer<'info>,
+}
+
 #[account]
 pub struct AccessControl {
     pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>,
     pub is_paused: bool,
-    pub permissions: Vec<(String, bool)>,
+    pub roles: Vec<RoleMembers>,
+}
+
+impl AccessControl {
+    pub const SPACE: usize = 8 + 32 + (1 + 32) + 1 + 4 + MAX_ROLES * RoleMembers::SPACE;
+
+    /// The admin key implicitly holds the `Admin` role.
+    pub fn has_role(&self, role: Role, account: &Pubkey) -> bool {
+        if role == Role::Admin && *account == self.admin {
+            return true;
+        }
+        self.roles
+            .iter()
+            .any(|r| r.role == role && r.members.contains(account))
+    }
+
+    /// 緊急暫停可由管理員或 Pauser 執行，恢復仍僅限管理員
+    pub fn can_pause(&self, account: &Pubkey) -> bool {
+        self.has_role(Role::Admin, account) || self.has_role(Role::Pauser, account)
+    }
+
+    pub fn grant(&mut self, role: Role, account: Pubkey) -> Result<()> {
+        require!(account != Pubkey::default(), AccessControlError::InvalidPermission);
+
+        let index = match self.roles.iter().position(|r| r.role == role) {
+            Some(index) => index,
+            None => {
+                require!(self.roles.len() < MAX_ROLES, AccessControlError::RoleCapacityExceeded);
+                self.roles.push(RoleMembers { role, members: Vec::new() });
+                self.roles.len() - 1
+            }
+        };
+
+        let entry = &mut self.roles[index];
+        require!(!entry.members.contains(&account), AccessControlError::RoleAlreadyGranted);
+        require!(entry.members.len() < MAX_MEMBERS_PER_ROLE, AccessControlError::RoleCapacityExceeded);
+        entry.members.push(account);
+        Ok(())
+    }
+
+    pub fn revoke(&mut self, role: Role, account: Pubkey) -> Result<()> {
+        let index = self.roles
+            .iter()
+            .position(|r| r.role == role)
+            .ok_or(AccessControlError::RoleNotGranted)?;
+        let entry = &mut self.roles[index];
+        let member_index = entry.members
+            .iter()
+            .position(|m| *m == account)
+            .ok_or(AccessControlError::RoleNotGranted)?;
+        entry.members.remove(member_index);
+
+        if entry.members.is_empty() {
+            self.roles.remove(index);
+        }
+        Ok(())
+    }
+
+    pub fn propose_admin(&mut self, new_admin: Pubkey) -> Result<()> {
+        require!(
+            new_admin != Pubkey::default() && new_admin != self.admin,
+            AccessControlError::InvalidAdmin
+        );
+        self.pending_admin = Some(new_admin);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    Admin,
+    Pauser,
+    OracleUpdater,
+    Minter,
+    Treasurer,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RoleMembers {
+    pub role: Role,
+    pub members: Vec<Pubkey>,
+}
+
+impl RoleMembers {
+    pub const SPACE: usize = 1 + 4 + 32 * MAX_MEMBERS_PER_ROLE;
+}
+
+#[account]
+pub struct Multisig {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub proposal_count: u64,
+    /// 每次 SetMultisig 換屆後遞增，舊屆的提案不能再核准或執行
+    pub generation: u64,
+    pub bump: u8,
+}
+
+impl Multisig {
+    pub const SPACE: usize = 8 + 4 + 32 * MAX_MULTISIG_SIGNERS + 1 + 8 + 8 + 1;
+}
+
+#[account]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: ProposalAction,
+    pub approvals: Vec<Pubkey>,
+    pub executed: bool,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub generation: u64,
+}
+
+impl Proposal {
+    pub const SPACE: usize = 8 + 8 + 32 + ProposalAction::MAX_SPACE + 4 + 32 * MAX_MULTISIG_SIGNERS + 1 + 8 + 8 + 8;
+
+    /// 提案必須屬於目前這一屆簽署者且尚未過期
+    fn check_open(&self, multisig: &Multisig) -> Result<()> {
+        require!(self.generation == multisig.generation, AccessControlError::StaleProposal);
+        require!(Clock::get()?.unix_timestamp < self.expires_at, AccessControlError::ProposalExpired);
+        Ok(())
+    }
+}
+
+/// Critical admin actions that require multisig approval.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum ProposalAction {
+    EmergencyStop,
+    Resume,
+    AcceptAdmin,
+    CloseAccount { recipient: Pubkey },
+    GrantRole { role: Role, account: Pubkey },
+    RevokeRole { role: Role, account: Pubkey },
+    ProposeAdmin { new_admin: Pubkey },
+    AssetManager { call: AssetManagerCall },
+    SetMultisig { signers: Vec<Pubkey>, threshold: u8 },
+}
+
+impl ProposalAction {
+    pub const MAX_SPACE: usize = 1 + 4 + 32 * MAX_MULTISIG_SIGNERS + 1;
+
+    fn requires_admin(&self) -> bool {
+        !matches!(self, Self::AcceptAdmin | Self::AssetManager { .. } | Self::SetMultisig { .. })
+    }
+}
+
+/// multisig 可代為簽署的 asset_manager 管理指令（允許清單），參數與 asset_manager 的指令一致
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum AssetManagerCall {
+    PauseSystem,
+    UnpauseSystem,
+    AcceptXxusdMintAuthority,
+    TransferAuthority { new_authority: Pubkey },
+    AddCollateral { mint: Pubkey, ltv_bps: u16, deposit_cap: u64 },
+    UpdateCollateral { mint: Pubkey, ltv_bps: u16, deposit_cap: u64, enabled: bool },
+    SetMintingLimits { global_limit: u64, user_limit: u64, epoch_limit: u64, epoch_duration: i64 },
+    QueueTimelockDelayUpdate { new_delay: i64 },
+    QueueApyUpdate { new_apy: u64 },
+    QueuePriceUpdate { new_price: u64 },
+    CancelQueuedUpdate { kind: QueuedUpdateKind },
+}
+
+/// 與 asset_manager::QueuedUpdateKind 相同的編碼
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QueuedUpdateKind {
+    Apy,
+    ProductPrice,
+    TimelockDelay,
+}
+
+impl AssetManagerCall {
+    fn data(&self) -> Result<Vec<u8>> {
+        let (name, args) = match self {
+            Self::PauseSystem => ("pause_system", Vec::new()),
+            Self::UnpauseSystem => ("unpause_system", Vec::new()),
+            Self::AcceptXxusdMintAuthority => ("accept_xxusd_mint_authority", Vec::new()),
+            Self::TransferAuthority { new_authority } => ("transfer_authority", new_authority.try_to_vec()?),
+            Self::AddCollateral { ltv_bps, deposit_cap, .. } => {
+                ("add_collateral", (*ltv_bps, *deposit_cap).try_to_vec()?)
+            }
+            Self::UpdateCollateral { ltv_bps, deposit_cap, enabled, .. } => {
+                ("update_collateral", (*ltv_bps, *deposit_cap, *enabled).try_to_vec()?)
+            }
+            Self::SetMintingLimits { global_limit, user_limit, epoch_limit, epoch_duration } => (
+                "set_minting_limits",
+                (*global_limit, *user_limit, *epoch_limit, *epoch_duration).try_to_vec()?,
+            ),
+            Self::QueueTimelockDelayUpdate { new_delay } => ("queue_timelock_delay_update", new_delay.try_to_vec()?),
+            Self::QueueApyUpdate { new_apy } => ("queue_apy_update", new_apy.try_to_vec()?),
+            Self::QueuePriceUpdate { new_price } => ("queue_price_update", new_price.try_to_vec()?),
+            Self::CancelQueuedUpdate { kind } => ("cancel_queued_update", kind.try_to_vec()?),
+        };
+        Ok(asset_manager_ix_data(name, args))
+    }
+
+    /// 提案綁定的目標帳戶：(在指令帳戶中的位置, 預期的位址)，避免執行者換成其他抵押品
+    fn bound_account(&self) -> Option<(usize, Pubkey)> {
+        match self {
+            Self::AddCollateral { mint, .. } => Some((1, *mint)),
+            Self::UpdateCollateral { mint, .. } => {
+                let (collateral, _) =
+                    Pubkey::find_program_address(&[b"collateral", mint.as_ref()], &ASSET_MANAGER_PROGRAM_ID);
+                Some((1, collateral))
+            }
+            _ => None,
+        }
+    }
 }
 
 #[error_code]
@@ -123,4 +739,148 @@ pub enum AccessControlError {
     AlreadyPaused,
     #[msg("System is not paused")]
     NotPaused,
+    #[msg("Role already granted to this account")]
+    RoleAlreadyGranted,
+    #[msg("Role not granted to this account")]
+    RoleNotGranted,
+    #[msg("Role capacity exceeded")]
+    RoleCapacityExceeded,
+    #[msg("Invalid admin")]
+    InvalidAdmin,
+    #[msg("No pending admin")]
+    NoPendingAdmin,
+    #[msg("Invalid multisig threshold")]
+    InvalidThreshold,
+    #[msg("Too many multisig signers")]
+    TooManySigners,
+    #[msg("Duplicate multisig signer")]
+    DuplicateSigner,
+    #[msg("Signer is not a multisig member")]
+    NotASigner,
+    #[msg("Proposal already approved by this signer")]
+    AlreadyApproved,
+    #[msg("Proposal already executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Approval threshold not met")]
+    ThresholdNotMet,
+    #[msg("Invalid target program")]
+    InvalidTargetProgram,
+    #[msg("Missing target accounts")]
+    MissingTargetAccounts,
+    #[msg("Target account does not match the proposal")]
+    InvalidTargetAccount,
+    #[msg("Proposal has expired")]
+    ProposalExpired,
+    #[msg("Proposal was created by a previous multisig signer set")]
+    StaleProposal,
+    #[msg("Calculation error")]
+    CalculationError,
+}
+
+#[event]
+pub struct RoleGrantedEvent {
+    pub role: Role,
+    pub account: Pubkey,
+    pub granted_by: Pubkey,
+}
+
+#[event]
+pub struct RoleRevokedEvent {
+    pub role: Role,
+    pub account: Pubkey,
+    pub revoked_by: Pubkey,
+}
+
+#[event]
+pub struct AdminProposedEvent {
+    pub current_admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminTransferredEvent {
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct MultisigUpdatedEvent {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct ProposalCreatedEvent {
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: ProposalAction,
+}
+
+#[event]
+pub struct ProposalApprovedEvent {
+    pub proposal_id: u64,
+    pub approver: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct ProposalExecutedEvent {
+    pub proposal_id: u64,
+    pub executor: Pubkey,
+    pub action: ProposalAction,
+}
+
+fn validate_multisig(signers: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(signers.len() <= MAX_MULTISIG_SIGNERS, AccessControlError::TooManySigners);
+    require!(
+        threshold > 0 && threshold as usize <= signers.len(),
+        AccessControlError::InvalidThreshold
+    );
+    for (i, signer) in signers.iter().enumerate() {
+        require!(!signers[i + 1..].contains(signer), AccessControlError::DuplicateSigner);
+    }
+    Ok(())
+}
+
+fn asset_manager_ix_data(ix_name: &str, args: Vec<u8>) -> Vec<u8> {
+    let mut data = hash(format!("global:{}", ix_name).as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(&args);
+    data
+}
+
+/// CPI 至 asset_manager 的允許清單指令，以 multisig PDA 作為其 authority 簽署。
+/// remaining_accounts: [asset_manager program, 目標指令的帳戶（依其定義順序）]
+fn invoke_asset_manager<'info>(
+    call: &AssetManagerCall,
+    multisig: &Account<'info, Multisig>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    let (target_program, accounts) = remaining_accounts
+        .split_first()
+        .ok_or(AccessControlError::MissingTargetAccounts)?;
+    require_keys_eq!(target_program.key(), ASSET_MANAGER_PROGRAM_ID, AccessControlError::InvalidTargetProgram);
+    if let Some((index, expected)) = call.bound_account() {
+        let account = accounts.get(index).ok_or(AccessControlError::MissingTargetAccounts)?;
+        require_keys_eq!(account.key(), expected, AccessControlError::InvalidTargetAccount);
+    }
+
+    let multisig_key = multisig.key();
+    let ix = Instruction {
+        program_id: target_program.key(),
+        accounts: accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer || account.key() == multisig_key,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: call.data()?,
+    };
+
+    let mut account_infos = accounts.to_vec();
+    account_infos.push(target_program.clone());
+    let seeds = &[b"multisig".as_ref(), &[multisig.bump]];
+    invoke_signed(&ix, &account_infos, &[&seeds[..]])?;
+    Ok(())
 }
@@ -1,6 +1,10 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Mint};
-use solana_program::pubkey::Pubkey;
+use anchor_spl::associated_token::AssociatedToken;
+use solana_program::{program_option::COption, pubkey::Pubkey};
+use access_control::{AccessControl, Role};
+use price_oracle::{LstAsset, PriceHistory, LST_PRICE_DECIMALS};
+use lock_manager::program::LockManager;
 
 declare_id!("HiudtiK3QwUEiRWU3WB7h1azKvu7kpwbZyU1qBvaUUD6");
 
@@ -10,20 +14,55 @@ const MIN_LOCK_PERIOD: u64 = 1;
 const MAX_LOCK_PERIOD: u64 = 365;
 const MIN_PRODUCT_PRICE: u64 = 10;
 const MAX_PRODUCT_PRICE: u64 = 10000;
+const PRICE_DECIMALS: u32 = 6;
+const MAX_ORACLE_STALENESS: i64 = 300;
+/// 存款以此區間內的 TWAP 計價，避免瞬間價格操縱
+const DEPOSIT_TWAP_WINDOW: i64 = 600;
+const BPS_DENOMINATOR: u64 = 10_000;
+const DEFAULT_TIMELOCK_DELAY: i64 = 2 * 86400;
+const MIN_TIMELOCK_DELAY: i64 = 3600;
+const MAX_TIMELOCK_DELAY: i64 = 30 * 86400;
+
+// RedemptionManager 依賴本程式做 CPI，為避免循環依賴在此直接寫入其程式 ID
+pub const REDEMPTION_MANAGER_PROGRAM_ID: Pubkey = solana_program::pubkey!("8uUo5wwK7LykM53dX1wGM4iS8HCZFVZiD6PeQ1xUqKLA");
 
 #[program]
 pub mod asset_manager {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, jupsol_mint: Pubkey) -> Result<()> {
+    pub fn initialize(ctx: Context<Initialize>, oracle: Pubkey) -> Result<()> {
         let state = &mut ctx.accounts.state;
         state.is_initialized = true;
         state.is_paused = false;
-        state.jupsol_mint = jupsol_mint;
+        state.oracle = oracle;
         state.authority = ctx.accounts.authority.key();
         state.current_apy = 762; // Initialize APY to 7.62%
         state.last_apy_update = Clock::get()?.unix_timestamp;
         state.product_price = 1798; // Initialize product price to 1798
+        state.timelock_delay = DEFAULT_TIMELOCK_DELAY;
+        state.pending_apy = None;
+        state.pending_price = None;
+        state.pending_timelock_delay = None;
+        // xxUSD 的鑄幣與金庫權限 PDA，記下 canonical bump 供之後簽名
+        state.nonce = ctx.bumps.mint_authority;
+        state.xxusd_mint = ctx.accounts.xxusd_mint.key();
+        Ok(())
+    }
+
+    pub fn accept_xxusd_mint_authority(ctx: Context<AcceptXxUSDMintAuthority>) -> Result<()> {
+        let cpi_accounts = xxusd_token::cpi::accounts::SetMintAuthority {
+            mint: ctx.accounts.xxusd_mint.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.xxusd_token_program.to_account_info(), cpi_accounts);
+        xxusd_token::cpi::set_mint_authority(cpi_ctx, ctx.accounts.mint_authority.key())?;
+
+        emit!(XxUSDMintAuthorityAcceptedEvent {
+            mint: ctx.accounts.xxusd_mint.key(),
+            mint_authority: ctx.accounts.mint_authority.key(),
+        });
+
         Ok(())
     }
 
@@ -45,18 +84,127 @@ pub mod asset_manager {
         Ok(())
     }
 
+    pub fn transfer_authority(ctx: Context<TransferAuthority>, new_authority: Pubkey) -> Result<()> {
+        require!(new_authority != Pubkey::default(), AssetManagerError::UnauthorizedAccount);
+
+        let state = &mut ctx.accounts.state;
+        let old_authority = state.authority;
+        state.authority = new_authority;
+
+        emit!(AuthorityTransferredEvent {
+            old_authority,
+            new_authority,
+        });
+
+        msg!("Authority transferred from {} to {}", old_authority, new_authority);
+
+        Ok(())
+    }
+
+    pub fn add_collateral(ctx: Context<AddCollateral>, ltv_bps: u16, deposit_cap: u64) -> Result<()> {
+        validate_collateral_params(ltv_bps, deposit_cap)?;
+
+        let collateral = &mut ctx.accounts.collateral;
+        collateral.mint = ctx.accounts.asset_mint.key();
+        collateral.lst_asset = ctx.accounts.lst_asset.key();
+        collateral.ltv_bps = ltv_bps;
+        collateral.deposit_cap = deposit_cap;
+        collateral.total_deposited = 0;
+        collateral.enabled = true;
+        collateral.bump = ctx.bumps.collateral;
+        collateral.vault_bump = ctx.bumps.vault_asset_account;
+
+        emit!(CollateralRegisteredEvent {
+            mint: collateral.mint,
+            lst_asset: collateral.lst_asset,
+            ltv_bps,
+            deposit_cap,
+        });
+
+        msg!("Collateral {} registered: LTV {} bps, cap {}", collateral.mint, ltv_bps, deposit_cap);
+
+        Ok(())
+    }
+
+    pub fn update_collateral(ctx: Context<UpdateCollateral>, ltv_bps: u16, deposit_cap: u64, enabled: bool) -> Result<()> {
+        validate_collateral_params(ltv_bps, deposit_cap)?;
+
+        let collateral = &mut ctx.accounts.collateral;
+        collateral.ltv_bps = ltv_bps;
+        collateral.deposit_cap = deposit_cap;
+        collateral.enabled = enabled;
+
+        emit!(CollateralUpdatedEvent {
+            mint: collateral.mint,
+            ltv_bps,
+            deposit_cap,
+            enabled,
+        });
+
+        msg!("Collateral {} updated: LTV {} bps, cap {}, enabled {}", collateral.mint, ltv_bps, deposit_cap, enabled);
+
+        Ok(())
+    }
+
+    pub fn set_minting_limits(
+        ctx: Context<SetMintingLimits>,
+        global_limit: u64,
+        user_limit: u64,
+        epoch_limit: u64,
+        epoch_duration: i64,
+    ) -> Result<()> {
+        require!(
+            global_limit > 0 && user_limit > 0 && epoch_limit > 0 && epoch_duration > 0,
+            AssetManagerError::InvalidMintingLimits
+        );
+        require!(
+            user_limit <= global_limit && epoch_limit <= global_limit,
+            AssetManagerError::InvalidMintingLimits
+        );
+
+        let state = &mut ctx.accounts.state;
+        state.minting_limit = global_limit;
+        state.user_minting_limit = user_limit;
+        state.epoch_minting_limit = epoch_limit;
+        state.epoch_duration = epoch_duration;
+
+        emit!(MintingLimitsUpdatedEvent {
+            global_limit,
+            user_limit,
+            epoch_limit,
+            epoch_duration,
+        });
+
+        msg!(
+            "Minting limits updated: global {}, per user {}, per epoch {} over {}s",
+            global_limit, user_limit, epoch_limit, epoch_duration
+        );
+
+        Ok(())
+    }
+
     pub fn deposit_asset(ctx: Context<DepositAsset>, amount: u64) -> Result<()> {
         require!(!ctx.accounts.state.is_paused, AssetManagerError::SystemPaused);
-        require!(ctx.accounts.asset_mint.key() == ctx.accounts.state.jupsol_mint, AssetManagerError::InvalidAssetType);
+        require!(!ctx.accounts.access_control.is_paused, AssetManagerError::SystemPaused);
+        require!(ctx.accounts.collateral.enabled, AssetManagerError::CollateralDisabled);
         require!(amount > 0, AssetManagerError::InvalidAmount);
 
-        let asset_price = get_asset_price(&ctx.accounts.oracle, &ctx.accounts.asset_mint.key())?;
-
-        let deposit_value = (amount as u128)
-            .checked_mul(asset_price as u128)
-            .ok_or(AssetManagerError::CalculationError)?
-            .checked_div(10u128.pow(ctx.accounts.asset_mint.decimals as u32) as u128)
+        let total_deposited = ctx.accounts.collateral.total_deposited
+            .checked_add(amount)
             .ok_or(AssetManagerError::CalculationError)?;
+        require!(total_deposited <= ctx.accounts.collateral.deposit_cap, AssetManagerError::DepositCapExceeded);
+
+        require!(!ctx.accounts.price_history.halted, AssetManagerError::OracleHalted);
+
+        let asset_price = get_asset_price(&ctx.accounts.lst_asset, &ctx.accounts.price_history)?;
+
+        // 存入的價值先依抵押品的 LTV 折扣，記錄的是可支撐 xxUSD 的額度
+        let deposit_value = collateral_value(
+            amount,
+            asset_price,
+            ctx.accounts.asset_mint.decimals,
+            ctx.accounts.collateral.ltv_bps,
+        )?;
 
         require!(ctx.accounts.user_asset_account.amount >= amount, AssetManagerError::InsufficientBalance);
 
@@ -69,14 +217,22 @@ pub mod asset_manager {
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
         token::transfer(cpi_ctx, amount)?;
 
-        ctx.accounts.user_deposit.amount = ctx.accounts.user_deposit.amount
-            .checked_add(deposit_value as u64)
+        ctx.accounts.collateral.total_deposited = total_deposited;
+
+        let user_deposit = &mut ctx.accounts.user_deposit;
+        user_deposit.mint = ctx.accounts.asset_mint.key();
+        user_deposit.collateral_amount = user_deposit.collateral_amount
+            .checked_add(amount)
+            .ok_or(AssetManagerError::CalculationError)?;
+        user_deposit.amount = user_deposit.amount
+            .checked_add(deposit_value)
             .ok_or(AssetManagerError::CalculationError)?;
 
         emit!(DepositEvent {
             user: ctx.accounts.user.key(),
+            mint: ctx.accounts.asset_mint.key(),
             amount,
-            value: deposit_value as u64,
+            value: deposit_value,
         });
 
         msg!("Deposit successful: {} tokens deposited, value: {}", amount, deposit_value);
@@ -84,19 +240,120 @@ pub mod asset_manager {
         Ok(())
     }
 
-    pub fn mint_and_distribute_xxusd(ctx: Context<MintAndDistributeXxUSD>, asset_value: u64, product_price: u64) -> Result<()> {
+    /// 償還 xxUSD 取回抵押品。xxusd_amount 包含鑄造時鎖入 lock_manager 的部分，
+    /// 用戶須先領回鎖倉的 xxUSD 才能全數償還並取回全部抵押品
+    pub fn withdraw_asset(ctx: Context<WithdrawAsset>, repay_amount: u64) -> Result<()> {
         require!(!ctx.accounts.state.is_paused, AssetManagerError::SystemPaused);
+        require!(!ctx.accounts.access_control.is_paused, AssetManagerError::SystemPaused);
+
+        let user_deposit = &ctx.accounts.user_deposit;
+        require!(user_deposit.collateral_amount > 0, AssetManagerError::InvalidAmount);
+        require!(repay_amount <= user_deposit.xxusd_amount, AssetManagerError::InvalidAmount);
+        require!(
+            repay_amount > 0 || user_deposit.xxusd_amount == 0,
+            AssetManagerError::InvalidAmount
+        );
+
+        // 依償還的 xxUSD 比例取回抵押品，全數償還時取回剩餘的全部抵押品
+        let remaining_xxusd = user_deposit.xxusd_amount - repay_amount;
+        let withdraw_amount = if remaining_xxusd == 0 {
+            user_deposit.collateral_amount
+        } else {
+            proportional(user_deposit.collateral_amount, repay_amount, user_deposit.xxusd_amount)?
+        };
+        require!(withdraw_amount > 0, AssetManagerError::InvalidAmount);
+
+        let remaining_collateral = user_deposit.collateral_amount - withdraw_amount;
+        let remaining_value = user_deposit.amount
+            - proportional(user_deposit.amount, withdraw_amount, user_deposit.collateral_amount)?;
+
+        // 仍有未償還的 xxUSD 時，剩餘抵押品以目前價格折算後必須足以支撐
+        if remaining_xxusd > 0 {
+            require!(!ctx.accounts.price_history.halted, AssetManagerError::OracleHalted);
+            let asset_price = get_asset_price(&ctx.accounts.lst_asset, &ctx.accounts.price_history)?;
+            let backing = collateral_value(
+                remaining_collateral,
+                asset_price,
+                ctx.accounts.asset_mint.decimals,
+                ctx.accounts.collateral.ltv_bps,
+            )?;
+            require!(backing >= remaining_xxusd, AssetManagerError::InsufficientCollateral);
+        }
+
+        if repay_amount > 0 {
+            let burn_accounts = token::Burn {
+                mint: ctx.accounts.xxusd_mint.to_account_info(),
+                from: ctx.accounts.user_xxusd_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            };
+            let burn_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), burn_accounts);
+            token::burn(burn_ctx, repay_amount)?;
+        }
+
+        let mint_key = ctx.accounts.asset_mint.key();
+        let seeds = &[
+            b"vault".as_ref(),
+            mint_key.as_ref(),
+            &[ctx.accounts.collateral.vault_bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let transfer_accounts = token::Transfer {
+            from: ctx.accounts.vault_asset_account.to_account_info(),
+            to: ctx.accounts.user_asset_account.to_account_info(),
+            authority: ctx.accounts.vault_asset_account.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+            signer
+        );
+        token::transfer(transfer_ctx, withdraw_amount)?;
 
-        let total_xxusd_amount = asset_value;
-        let locked_xxusd_amount = product_price;
-        let user_xxusd_amount = total_xxusd_amount
-            .checked_sub(locked_xxusd_amount)
+        ctx.accounts.collateral.total_deposited = ctx.accounts.collateral.total_deposited
+            .checked_sub(withdraw_amount)
             .ok_or(AssetManagerError::CalculationError)?;
 
+        ctx.accounts.state.record_repay(&mut ctx.accounts.user_mint, repay_amount);
+
+        let user_deposit = &mut ctx.accounts.user_deposit;
+        user_deposit.collateral_amount = remaining_collateral;
+        user_deposit.amount = remaining_value;
+        user_deposit.xxusd_amount = remaining_xxusd;
+
+        emit!(WithdrawEvent {
+            user: ctx.accounts.user.key(),
+            mint: mint_key,
+            amount: withdraw_amount,
+            repaid: repay_amount,
+        });
+
+        msg!("Withdrawal successful: {} tokens returned, {} xxUSD repaid", withdraw_amount, repay_amount);
+
+        Ok(())
+    }
+
+    pub fn mint_and_distribute_xxusd(ctx: Context<MintAndDistributeXxUSD>) -> Result<()> {
+        require!(!ctx.accounts.state.is_paused, AssetManagerError::SystemPaused);
+        require!(!ctx.accounts.access_control.is_paused, AssetManagerError::SystemPaused);
+        require!(ctx.accounts.collateral.enabled, AssetManagerError::CollateralDisabled);
+        require!(!ctx.accounts.price_history.halted, AssetManagerError::OracleHalted);
+
+        // 可鑄造的數量為存款折算價值扣除已鑄造的部分，商品價格的部分留在金庫
+        let total_xxusd_amount = ctx.accounts.user_deposit.amount
+            .checked_sub(ctx.accounts.user_deposit.xxusd_amount)
+            .ok_or(AssetManagerError::CalculationError)?;
+        let locked_xxusd_amount = ctx.accounts.state.product_price
+            .checked_mul(10u64.pow(PRICE_DECIMALS))
+            .ok_or(AssetManagerError::CalculationError)?;
         require!(
-            total_xxusd_amount <= ctx.accounts.state.minting_limit,
-            AssetManagerError::MintingLimitExceeded
+            total_xxusd_amount > 0 && total_xxusd_amount >= locked_xxusd_amount,
+            AssetManagerError::InsufficientDepositValue
         );
+        let user_xxusd_amount = total_xxusd_amount - locked_xxusd_amount;
+
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.state.record_mint(&mut ctx.accounts.user_mint, total_xxusd_amount, now)?;
 
         let seeds = &[
             ctx.accounts.state.to_account_info().key.as_ref(),
@@ -125,8 +382,34 @@ pub mod asset_manager {
         );
         token::transfer(transfer_ctx, user_xxusd_amount)?;
 
+        // 商品價格的部分鎖入 lock_manager 的新部位，依抵押品收益推算鎖定天數並逐日釋放
+        // 每日額度向下取整，整除後的零頭在鎖定期結束時隨剩餘餘額一併釋放
+        let lock_period = lock_period_days(locked_xxusd_amount, total_xxusd_amount, ctx.accounts.state.current_apy)?;
+        let daily_release = locked_xxusd_amount / lock_period;
+        let lock_accounts = lock_manager::cpi::accounts::LockMintedXxUSD {
+            user: ctx.accounts.user.to_account_info(),
+            xxusd_mint: ctx.accounts.xxusd_mint.to_account_info(),
+            source: ctx.accounts.xxusd_vault.to_account_info(),
+            source_authority: ctx.accounts.vault_authority.to_account_info(),
+            asset_manager_state: ctx.accounts.state.to_account_info(),
+            lock_vault: ctx.accounts.lock_vault.to_account_info(),
+            lock_manager: ctx.accounts.lock_manager.to_account_info(),
+            lock_index: ctx.accounts.lock_index.to_account_info(),
+            lock_record: ctx.accounts.lock_record.to_account_info(),
+            access_control: ctx.accounts.access_control.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+        let lock_ctx = CpiContext::new_with_signer(
+            ctx.accounts.lock_manager_program.to_account_info(),
+            lock_accounts,
+            signer
+        );
+        lock_manager::cpi::lock_minted_xxusd(lock_ctx, locked_xxusd_amount, lock_period, daily_release)?;
+
         ctx.accounts.user_deposit.xxusd_amount = ctx.accounts.user_deposit.xxusd_amount
-            .checked_add(user_xxusd_amount)
+            .checked_add(total_xxusd_amount)
             .ok_or(AssetManagerError::CalculationError)?;
 
         emit!(MintAndDistributeEvent {
@@ -134,30 +417,44 @@ pub mod asset_manager {
             total_amount: total_xxusd_amount,
             locked_amount: locked_xxusd_amount,
             user_amount: user_xxusd_amount,
+            lock_period,
+            daily_release,
         });
 
-        msg!("xxUSD minted and distributed: total {}, locked {}, user {}", total_xxusd_amount, locked_xxusd_amount, user_xxusd_amount);
+        msg!(
+            "xxUSD minted and distributed: total {}, locked {} for {} days, user {}",
+            total_xxusd_amount, locked_xxusd_amount, lock_period, user_xxusd_amount
+        );
+
+        Ok(())
+    }
+
+    /// 贖回銷毀的 xxUSD 由 redemption_manager 以 CPI 回報，與償還相同地釋出流通量與用戶額度
+    pub fn record_redemption_burn(ctx: Context<RecordRedemptionBurn>, amount: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        match ctx.accounts.user_mint.as_mut() {
+            Some(user_mint) => state.record_repay(user_mint, amount),
+            // 鎖倉的 xxUSD 不一定由贖回者鑄造，沒有鑄造紀錄時只釋出流通量
+            None => state.outstanding_supply = state.outstanding_supply.saturating_sub(amount),
+        }
+
+        emit!(RedemptionBurnRecordedEvent {
+            user: ctx.accounts.user.key(),
+            amount,
+            outstanding_supply: ctx.accounts.state.outstanding_supply,
+        });
+
+        msg!("Redemption burn of {} xxUSD recorded for {}", amount, ctx.accounts.user.key());
 
         Ok(())
     }
 
     pub fn calculate_lock_period(ctx: Context<CalculateLockPeriod>, product_price: u64, asset_value: u64) -> Result<u64> {
         let apy = ctx.accounts.state.current_apy;
-        require!(apy > 0, AssetManagerError::InvalidAPY);
 
         msg!("Calculating lock period with product_price: {}, asset_value: {}, apy: {}", product_price, asset_value, apy);
 
-        let lock_period = (product_price as u128)
-            .checked_mul(DAYS_IN_YEAR as u128)
-            .and_then(|result| result.checked_mul(APY_PRECISION as u128))
-            .and_then(|result| {
-                let denominator = (asset_value as u128).checked_mul(apy as u128)
-                    .ok_or(AssetManagerError::CalculationError).ok()?;
-                result.checked_div(denominator)
-            })
-            .ok_or(AssetManagerError::CalculationError)?;
-
-        let lock_period = lock_period.clamp(MIN_LOCK_PERIOD as u128, MAX_LOCK_PERIOD as u128) as u64;
+        let lock_period = lock_period_days(product_price, asset_value, apy)?;
 
         emit!(LockPeriodCalculatedEvent {
             product_price,
@@ -171,45 +468,160 @@ pub mod asset_manager {
         Ok(lock_period)
     }
 
-    pub fn update_apy(ctx: Context<UpdateAPY>, new_apy: u64) -> Result<()> {
+    pub fn queue_timelock_delay_update(ctx: Context<QueueUpdate>, new_delay: i64) -> Result<()> {
+        require!(
+            (MIN_TIMELOCK_DELAY..=MAX_TIMELOCK_DELAY).contains(&new_delay),
+            AssetManagerError::InvalidTimelockDelay
+        );
+
+        let state = &mut ctx.accounts.state;
+        require!(state.pending_timelock_delay.is_none(), AssetManagerError::UpdateAlreadyQueued);
+
+        // 延遲本身的變更也要等待目前的延遲時間，避免縮短後立即生效
+        let eta = Clock::get()?.unix_timestamp
+            .checked_add(state.timelock_delay)
+            .ok_or(AssetManagerError::CalculationError)?;
+        state.pending_timelock_delay = Some(QueuedUpdate { new_value: new_delay as u64, eta });
+
+        emit!(UpdateQueuedEvent {
+            kind: QueuedUpdateKind::TimelockDelay,
+            old_value: state.timelock_delay as u64,
+            new_value: new_delay as u64,
+            eta,
+        });
+
+        msg!("Timelock delay update to {} queued, executable at {}", new_delay, eta);
+
+        Ok(())
+    }
+
+    pub fn queue_apy_update(ctx: Context<QueueUpdate>, new_apy: u64) -> Result<()> {
         require!(new_apy > 0, AssetManagerError::InvalidAPY);
-        
+
         let state = &mut ctx.accounts.state;
-        let old_apy = state.current_apy;
-        state.current_apy = new_apy;
-        state.last_apy_update = Clock::get()?.unix_timestamp;
+        require!(state.pending_apy.is_none(), AssetManagerError::UpdateAlreadyQueued);
+
+        let eta = Clock::get()?.unix_timestamp
+            .checked_add(state.timelock_delay)
+            .ok_or(AssetManagerError::CalculationError)?;
+        state.pending_apy = Some(QueuedUpdate { new_value: new_apy, eta });
 
-        emit!(APYUpdatedEvent {
-            old_apy,
-            new_apy,
-            timestamp: state.last_apy_update,
+        emit!(UpdateQueuedEvent {
+            kind: QueuedUpdateKind::Apy,
+            old_value: state.current_apy,
+            new_value: new_apy,
+            eta,
         });
 
-        msg!("APY updated from {} to {}", old_apy, new_apy);
+        msg!("APY update to {} queued, executable at {}", new_apy, eta);
 
         Ok(())
     }
 
-    pub fn set_product_price(ctx: Context<SetProductPrice>, new_price: u64) -> Result<()> {
+    pub fn queue_price_update(ctx: Context<QueueUpdate>, new_price: u64) -> Result<()> {
         // 驗證價格的合理性
         require!(
-            new_price >= MIN_PRODUCT_PRICE && new_price <= MAX_PRODUCT_PRICE,
+            (MIN_PRODUCT_PRICE..=MAX_PRODUCT_PRICE).contains(&new_price),
             AssetManagerError::InvalidPrice
         );
 
-        let old_price = ctx.accounts.state.product_price;
-        
-        // 更新商品價格
-        ctx.accounts.state.product_price = new_price;
+        let state = &mut ctx.accounts.state;
+        require!(state.pending_price.is_none(), AssetManagerError::UpdateAlreadyQueued);
+
+        let eta = Clock::get()?.unix_timestamp
+            .checked_add(state.timelock_delay)
+            .ok_or(AssetManagerError::CalculationError)?;
+        state.pending_price = Some(QueuedUpdate { new_value: new_price, eta });
+
+        emit!(UpdateQueuedEvent {
+            kind: QueuedUpdateKind::ProductPrice,
+            old_value: state.product_price,
+            new_value: new_price,
+            eta,
+        });
+
+        msg!("Product price update to {} queued, executable at {}", new_price, eta);
+
+        Ok(())
+    }
+
+    pub fn execute_queued_update(ctx: Context<ExecuteQueuedUpdate>, kind: QueuedUpdateKind) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let now = Clock::get()?.unix_timestamp;
+
+        match kind {
+            QueuedUpdateKind::Apy => {
+                let queued = state.pending_apy.ok_or(AssetManagerError::NoQueuedUpdate)?;
+                require!(now >= queued.eta, AssetManagerError::TimelockNotExpired);
+
+                let old_apy = state.current_apy;
+                state.current_apy = queued.new_value;
+                state.last_apy_update = now;
+                state.pending_apy = None;
+
+                emit!(APYUpdatedEvent {
+                    old_apy,
+                    new_apy: queued.new_value,
+                    timestamp: now,
+                });
+
+                msg!("APY updated from {} to {}", old_apy, queued.new_value);
+            }
+            QueuedUpdateKind::ProductPrice => {
+                let queued = state.pending_price.ok_or(AssetManagerError::NoQueuedUpdate)?;
+                require!(now >= queued.eta, AssetManagerError::TimelockNotExpired);
+
+                let old_price = state.product_price;
+                
+                // 更新商品價格
+                state.product_price = queued.new_value;
+                state.pending_price = None;
+
+                // 記錄價格變更
+                emit!(PriceChangedEvent {
+                    old_price,
+                    new_price: queued.new_value,
+                    authority: ctx.accounts.executor.key(),
+                });
+
+                msg!("Product price updated from {} to {}", old_price, queued.new_value);
+            }
+            QueuedUpdateKind::TimelockDelay => {
+                let queued = state.pending_timelock_delay.ok_or(AssetManagerError::NoQueuedUpdate)?;
+                require!(now >= queued.eta, AssetManagerError::TimelockNotExpired);
+
+                let old_delay = state.timelock_delay;
+                let new_delay = queued.new_value as i64;
+                state.timelock_delay = new_delay;
+                state.pending_timelock_delay = None;
+
+                emit!(TimelockDelayChangedEvent {
+                    old_delay,
+                    new_delay,
+                });
+
+                msg!("Timelock delay updated from {} to {}", old_delay, new_delay);
+            }
+        }
 
-        // 記錄價格變更
-        emit!(PriceChangedEvent {
-            old_price,
-            new_price,
-            authority: ctx.accounts.authority.key(),
+        Ok(())
+    }
+
+    pub fn cancel_queued_update(ctx: Context<QueueUpdate>, kind: QueuedUpdateKind) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let queued = match kind {
+            QueuedUpdateKind::Apy => state.pending_apy.take(),
+            QueuedUpdateKind::ProductPrice => state.pending_price.take(),
+            QueuedUpdateKind::TimelockDelay => state.pending_timelock_delay.take(),
+        }
+        .ok_or(AssetManagerError::NoQueuedUpdate)?;
+
+        emit!(UpdateCancelledEvent {
+            kind,
+            new_value: queued.new_value,
         });
 
-        msg!("Product price updated from {} to {}", old_price, new_price);
+        msg!("Queued {:?} update to {} cancelled", kind, queued.new_value);
 
         Ok(())
     }
@@ -217,16 +629,59 @@ pub mod asset_manager {
 
 #[derive(Accounts)]
 pub struct Initialize<'info> {
-    #[account(init, payer = authority, space = 8 + 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8)]
+    /// 全程式唯一的狀態帳戶，所有以 authority 把關的指令都只接受這一個
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + (1 + 16) * 3 + 8 * 6 + 32,
+        seeds = [b"state"],
+        bump
+    )]
     pub state: Account<'info, ProgramState>,
-    #[account(mut)]
+    pub xxusd_mint: Account<'info, Mint>,
+    /// CHECK: This is the xxUSD mint and vault authority PDA, only used to derive its bump
+    #[account(seeds = [state.key().as_ref()], bump)]
+    pub mint_authority: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = xxusd_mint,
+        associated_token::authority = mint_authority,
+    )]
+    pub xxusd_vault: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    /// 只有存取控制的管理員能建立狀態帳戶，避免部署後被搶先初始化
+    #[account(mut, constraint = authority.key() == access_control.admin @ AssetManagerError::UnauthorizedAccount)]
     pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct AcceptXxUSDMintAuthority<'info> {
+    #[account(seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    pub state: Account<'info, ProgramState>,
+    #[account(mut, address = state.xxusd_mint @ AssetManagerError::InvalidXxUSDMint)]
+    pub xxusd_mint: Account<'info, Mint>,
+    /// CHECK: This is the xxUSD mint authority PDA receiving the mint authority
+    #[account(seeds = [state.key().as_ref()], bump = state.nonce)]
+    pub mint_authority: AccountInfo<'info>,
+    /// 必須同時是 xxUSD mint 目前的鑄幣權限
+    #[account(constraint = authority.key() == state.authority @ AssetManagerError::UnauthorizedAccount)]
+    pub authority: Signer<'info>,
+    pub xxusd_token_program: Program<'info, xxusd_token::program::XxusdToken>,
+    pub token_program: Program<'info, Token>,
+}
+
 #[derive(Accounts)]
 pub struct PauseSystem<'info> {
-    #[account(mut)]
+    #[account(mut, seeds = [b"state"], bump)]
     pub state: Account<'info, ProgramState>,
     pub authority: Signer<'info>,
 }
@@ -243,37 +698,169 @@ pub struct DepositAsset<'info> {
     )]
     pub user_asset_account: Account<'info, TokenAccount>,
     pub asset_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"collateral", asset_mint.key().as_ref()],
+        bump = collateral.bump
+    )]
+    pub collateral: Account<'info, CollateralConfig>,
     #[account(
         mut,
         seeds = [b"vault", asset_mint.key().as_ref()],
-        bump
+        bump = collateral.vault_bump
     )]
     pub vault_asset_account: Account<'info, TokenAccount>,
     #[account(
         init_if_needed,
         payer = user,
-        space = 8 + 8 + 8,
-        seeds = [b"user_deposit", user.key().as_ref()],
+        space = 8 + 8 + 8 + 32 + 8,
+        seeds = [b"user_deposit", user.key().as_ref(), asset_mint.key().as_ref()],
         bump
     )]
     pub user_deposit: Account<'info, UserDeposit>,
-    #[account(constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    #[account(seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
     pub state: Account<'info, ProgramState>,
-    /// CHECK: This account is not read or written in this instruction
-    pub oracle: AccountInfo<'info>,
+    #[account(address = collateral.lst_asset @ AssetManagerError::OracleError)]
+    pub lst_asset: Account<'info, LstAsset>,
+    #[account(
+        seeds = [b"history", lst_asset.key().as_ref()],
+        bump = price_history.bump,
+        seeds::program = price_oracle::ID,
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct MintAndDistributeXxUSD<'info> {
+pub struct RecordRedemptionBurn<'info> {
+    #[account(mut, seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    pub state: Account<'info, ProgramState>,
+    /// 只有實際掌握此 mint 鑄幣權的狀態帳戶才記帳
+    #[account(
+        address = state.xxusd_mint @ AssetManagerError::InvalidXxUSDMint,
+        constraint = xxusd_mint.mint_authority == COption::Some(mint_authority.key()) @ AssetManagerError::InvalidXxUSDMint
+    )]
+    pub xxusd_mint: Account<'info, Mint>,
+    /// CHECK: This is the xxUSD mint authority PDA of the state
+    #[account(seeds = [state.key().as_ref()], bump = state.nonce)]
+    pub mint_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"user_mint", user.key().as_ref()],
+        bump
+    )]
+    pub user_mint: Option<Account<'info, UserMintRecord>>,
+    /// CHECK: The redeeming user, only used to derive user_mint
+    pub user: AccountInfo<'info>,
+    /// 必須是 redemption_manager 的 PDA，確保只有實際銷毀時才能回報
+    #[account(
+        seeds = [b"redemption_manager"],
+        bump,
+        seeds::program = REDEMPTION_MANAGER_PROGRAM_ID,
+    )]
+    pub redemption_manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawAsset<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_asset_account.owner == user.key() @ AssetManagerError::UnauthorizedAccount,
+        constraint = user_asset_account.mint == asset_mint.key() @ AssetManagerError::InvalidAssetAccount
+    )]
+    pub user_asset_account: Account<'info, TokenAccount>,
+    pub asset_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"collateral", asset_mint.key().as_ref()],
+        bump = collateral.bump
+    )]
+    pub collateral: Account<'info, CollateralConfig>,
+    #[account(
+        mut,
+        seeds = [b"vault", asset_mint.key().as_ref()],
+        bump = collateral.vault_bump
+    )]
+    pub vault_asset_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"user_deposit", user.key().as_ref(), asset_mint.key().as_ref()],
+        bump
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+    #[account(
+        mut,
+        constraint = user_xxusd_account.owner == user.key() @ AssetManagerError::UnauthorizedAccount,
+        constraint = user_xxusd_account.mint == xxusd_mint.key() @ AssetManagerError::InvalidAssetAccount
+    )]
+    pub user_xxusd_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = state.xxusd_mint @ AssetManagerError::InvalidXxUSDMint,
+        constraint = xxusd_mint.mint_authority == COption::Some(mint_authority.key()) @ AssetManagerError::InvalidXxUSDMint
+    )]
     pub xxusd_mint: Account<'info, Mint>,
+    /// CHECK: This is the xxUSD mint authority PDA, used only to identify the xxUSD mint
+    #[account(seeds = [state.to_account_info().key.as_ref()], bump = state.nonce)]
+    pub mint_authority: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 8,
+        seeds = [b"user_mint", user.key().as_ref()],
+        bump
+    )]
+    pub user_mint: Account<'info, UserMintRecord>,
+    #[account(mut, seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    pub state: Account<'info, ProgramState>,
+    #[account(address = collateral.lst_asset @ AssetManagerError::OracleError)]
+    pub lst_asset: Account<'info, LstAsset>,
+    #[account(
+        seeds = [b"history", lst_asset.key().as_ref()],
+        bump = price_history.bump,
+        seeds::program = price_oracle::ID,
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintAndDistributeXxUSD<'info> {
     #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        address = state.xxusd_mint @ AssetManagerError::InvalidXxUSDMint,
+        constraint = xxusd_mint.mint_authority == COption::Some(mint_authority.key()) @ AssetManagerError::InvalidXxUSDMint
+    )]
+    pub xxusd_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = xxusd_mint,
+        associated_token::authority = vault_authority,
+    )]
     pub xxusd_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
+    #[account(
+        mut,
+        token::mint = state.xxusd_mint,
+        token::authority = user,
+    )]
     pub user_xxusd_account: Account<'info, TokenAccount>,
     /// CHECK: This account is used as the mint authority
     #[account(seeds = [state.to_account_info().key.as_ref()], bump = state.nonce)]
@@ -281,61 +868,267 @@ pub struct MintAndDistributeXxUSD<'info> {
     /// CHECK: This account is used as the vault authority
     #[account(seeds = [state.to_account_info().key.as_ref()], bump = state.nonce)]
     pub vault_authority: AccountInfo<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"user_deposit", user.key().as_ref(), collateral.mint.as_ref()],
+        bump
+    )]
     pub user_deposit: Account<'info, UserDeposit>,
-    #[account(constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 8,
+        seeds = [b"user_mint", user.key().as_ref()],
+        bump
+    )]
+    pub user_mint: Account<'info, UserMintRecord>,
+    #[account(mut, seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
     pub state: Account<'info, ProgramState>,
+    #[account(
+        seeds = [b"collateral", collateral.mint.as_ref()],
+        bump = collateral.bump
+    )]
+    pub collateral: Account<'info, CollateralConfig>,
+    #[account(address = collateral.lst_asset @ AssetManagerError::OracleError)]
+    pub lst_asset: Account<'info, LstAsset>,
+    #[account(
+        seeds = [b"history", lst_asset.key().as_ref()],
+        bump = price_history.bump,
+        seeds::program = price_oracle::ID,
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    /// CHECK: LockManager 的 xxUSD 金庫，由 lock_manager 驗證
+    #[account(mut)]
+    pub lock_vault: AccountInfo<'info>,
+    /// CHECK: LockManager 的 PDA，由 lock_manager 驗證
+    #[account(seeds = [b"lock_manager"], bump, seeds::program = lock_manager::ID)]
+    pub lock_manager: AccountInfo<'info>,
+    /// CHECK: 用戶的鎖倉索引，由 lock_manager 建立與驗證
+    #[account(
+        mut,
+        seeds = [b"lock_index", user.key().as_ref()],
+        bump,
+        seeds::program = lock_manager::ID,
+    )]
+    pub lock_index: AccountInfo<'info>,
+    /// CHECK: 以索引中下一個編號推導的鎖定紀錄，由 lock_manager 建立與驗證
+    #[account(mut)]
+    pub lock_record: AccountInfo<'info>,
+    pub lock_manager_program: Program<'info, LockManager>,
     pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct CalculateLockPeriod<'info> {
-    #[account(constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    #[account(seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
     pub state: Account<'info, ProgramState>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateAPY<'info> {
-    #[account(mut, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+pub struct AddCollateral<'info> {
+    #[account(seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
     pub state: Account<'info, ProgramState>,
+    pub asset_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 2 + 8 + 8 + 1 + 1 + 1,
+        seeds = [b"collateral", asset_mint.key().as_ref()],
+        bump
+    )]
+    pub collateral: Account<'info, CollateralConfig>,
+    #[account(
+        init,
+        payer = payer,
+        token::mint = asset_mint,
+        token::authority = vault_asset_account,
+        seeds = [b"vault", asset_mint.key().as_ref()],
+        bump
+    )]
+    pub vault_asset_account: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [b"lst", state.oracle.as_ref(), asset_mint.key().as_ref()],
+        bump = lst_asset.bump,
+        seeds::program = price_oracle::ID,
+    )]
+    pub lst_asset: Account<'info, LstAsset>,
     #[account(constraint = authority.key() == state.authority @ AssetManagerError::UnauthorizedAccount)]
     pub authority: Signer<'info>,
+    /// authority 為 multisig PDA 時無法支付租金，由執行者另行付款
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SetProductPrice<'info> {
-    #[account(mut, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+pub struct UpdateCollateral<'info> {
+    #[account(seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    pub state: Account<'info, ProgramState>,
+    #[account(
+        mut,
+        seeds = [b"collateral", collateral.mint.as_ref()],
+        bump = collateral.bump
+    )]
+    pub collateral: Account<'info, CollateralConfig>,
+    #[account(constraint = authority.key() == state.authority @ AssetManagerError::UnauthorizedAccount)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintingLimits<'info> {
+    #[account(mut, seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    pub state: Account<'info, ProgramState>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    /// 程式 authority 或持有 Minter 角色的帳戶
+    #[account(
+        constraint = authority.key() == state.authority
+            || access_control.has_role(Role::Minter, &authority.key()) @ AssetManagerError::UnauthorizedAccount
+    )]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueUpdate<'info> {
+    #[account(mut, seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    pub state: Account<'info, ProgramState>,
+    #[account(constraint = authority.key() == state.authority @ AssetManagerError::UnauthorizedAccount)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteQueuedUpdate<'info> {
+    #[account(mut, seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
+    pub state: Account<'info, ProgramState>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferAuthority<'info> {
+    #[account(mut, seeds = [b"state"], bump, constraint = state.is_initialized @ AssetManagerError::UninitializedState)]
     pub state: Account<'info, ProgramState>,
     #[account(constraint = authority.key() == state.authority @ AssetManagerError::UnauthorizedAccount)]
     pub authority: Signer<'info>,
 }
 
+/// 用戶在單一抵押品上的部位，`amount` 為扣除 LTV 後的抵押價值，`xxusd_amount` 為已鑄造的 xxUSD
 #[account]
 pub struct UserDeposit {
     pub amount: u64,
     pub xxusd_amount: u64,
+    pub mint: Pubkey,
+    pub collateral_amount: u64,
+}
+
+/// 可存入的抵押品設定，價格取自 price_oracle 中對應的 LstAsset
+#[account]
+pub struct CollateralConfig {
+    pub mint: Pubkey,
+    pub lst_asset: Pubkey,
+    pub ltv_bps: u16,
+    pub deposit_cap: u64,
+    pub total_deposited: u64,
+    pub enabled: bool,
+    pub bump: u8,
+    pub vault_bump: u8,
 }
 
 #[account]
 pub struct ProgramState {
     pub is_initialized: bool,
     pub is_paused: bool,
-    pub jupsol_mint: Pubkey,
+    pub oracle: Pubkey,
     pub authority: Pubkey,
     pub minting_limit: u64,
     pub nonce: u8,
     pub current_apy: u64,
     pub last_apy_update: i64,
     pub product_price: u64,
+    pub timelock_delay: i64,
+    pub pending_apy: Option<QueuedUpdate>,
+    pub pending_price: Option<QueuedUpdate>,
+    pub pending_timelock_delay: Option<QueuedUpdate>,
+    pub user_minting_limit: u64,
+    pub epoch_minting_limit: u64,
+    pub epoch_duration: i64,
+    pub outstanding_supply: u64,
+    pub epoch_start: i64,
+    pub epoch_minted: u64,
+    pub xxusd_mint: Pubkey,
+}
+
+impl ProgramState {
+    /// 檢查並累計一次鑄造：流通量上限（`minting_limit`）、單一用戶上限與滾動週期預算
+    pub fn record_mint(&mut self, user_mint: &mut UserMintRecord, amount: u64, now: i64) -> Result<()> {
+        let outstanding_supply = self.outstanding_supply
+            .checked_add(amount)
+            .ok_or(AssetManagerError::CalculationError)?;
+        require!(outstanding_supply <= self.minting_limit, AssetManagerError::MintingLimitExceeded);
+
+        let user_outstanding = user_mint.outstanding
+            .checked_add(amount)
+            .ok_or(AssetManagerError::CalculationError)?;
+        require!(user_outstanding <= self.user_minting_limit, AssetManagerError::UserMintingLimitExceeded);
+
+        // 週期結束後從當下重新起算
+        if now >= self.epoch_start.saturating_add(self.epoch_duration) {
+            self.epoch_start = now;
+            self.epoch_minted = 0;
+        }
+        let epoch_minted = self.epoch_minted
+            .checked_add(amount)
+            .ok_or(AssetManagerError::CalculationError)?;
+        require!(epoch_minted <= self.epoch_minting_limit, AssetManagerError::EpochMintingLimitExceeded);
+
+        self.outstanding_supply = outstanding_supply;
+        self.epoch_minted = epoch_minted;
+        user_mint.outstanding = user_outstanding;
+        Ok(())
+    }
+
+    /// 償還的 xxUSD 釋出流通量與用戶額度，週期預算不退回
+    pub fn record_repay(&mut self, user_mint: &mut UserMintRecord, amount: u64) {
+        self.outstanding_supply = self.outstanding_supply.saturating_sub(amount);
+        user_mint.outstanding = user_mint.outstanding.saturating_sub(amount);
+    }
+}
+
+/// 用戶跨所有抵押品已鑄造且未償還的 xxUSD
+#[account]
+pub struct UserMintRecord {
+    pub outstanding: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct QueuedUpdate {
+    pub new_value: u64,
+    pub eta: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QueuedUpdateKind {
+    Apy,
+    ProductPrice,
+    TimelockDelay,
 }
 
 #[error_code]
 pub enum AssetManagerError {
     #[msg("System is paused")]
     SystemPaused,
-    #[msg("Invalid asset type")]
-    InvalidAssetType,
     #[msg("Calculation error")]
     CalculationError,
     #[msg("Invalid amount")]
@@ -356,21 +1149,69 @@ pub enum AssetManagerError {
     InvalidAPY,
     #[msg("Invalid price")]
     InvalidPrice,
+    #[msg("Oracle price is stale")]
+    StaleOraclePrice,
+    #[msg("Oracle price is halted by the circuit breaker")]
+    OracleHalted,
+    #[msg("Invalid timelock delay")]
+    InvalidTimelockDelay,
+    #[msg("An update of this kind is already queued")]
+    UpdateAlreadyQueued,
+    #[msg("No queued update")]
+    NoQueuedUpdate,
+    #[msg("Timelock has not expired")]
+    TimelockNotExpired,
+    #[msg("Invalid collateral parameters")]
+    InvalidCollateralParams,
+    #[msg("Collateral is disabled")]
+    CollateralDisabled,
+    #[msg("Collateral deposit cap exceeded")]
+    DepositCapExceeded,
+    #[msg("Remaining collateral does not back the outstanding xxUSD")]
+    InsufficientCollateral,
+    #[msg("Invalid xxUSD mint")]
+    InvalidXxUSDMint,
+    #[msg("Deposit value does not cover the product price")]
+    InsufficientDepositValue,
+    #[msg("Invalid minting limits")]
+    InvalidMintingLimits,
+    #[msg("Per-user minting limit exceeded")]
+    UserMintingLimitExceeded,
+    #[msg("Epoch minting budget exceeded")]
+    EpochMintingLimitExceeded,
 }
 
 #[event]
 pub struct DepositEvent {
     pub user: Pubkey,
+    pub mint: Pubkey,
     pub amount: u64,
     pub value: u64,
 }
 
+#[event]
+pub struct WithdrawEvent {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub repaid: u64,
+}
+
+#[event]
+pub struct RedemptionBurnRecordedEvent {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub outstanding_supply: u64,
+}
+
 #[event]
 pub struct MintAndDistributeEvent {
     pub user: Pubkey,
     pub total_amount: u64,
     pub locked_amount: u64,
     pub user_amount: u64,
+    pub lock_period: u64,
+    pub daily_release: u64,
 }
 
 #[event]
@@ -395,8 +1236,118 @@ pub struct PriceChangedEvent {
     pub authority: Pubkey,
 }
 
-fn get_asset_price(oracle: &AccountInfo, asset_mint: &Pubkey) -> Result<u64> {
-    // TODO: Implement actual Oracle price fetching logic
-    msg!("Fetching price from Oracle for asset: {}", asset_mint);
-    Ok(1_000_000) // 假設價格，實際應從 Oracle 獲取
-}
\ No newline at end of file
+#[event]
+pub struct UpdateQueuedEvent {
+    pub kind: QueuedUpdateKind,
+    pub old_value: u64,
+    pub new_value: u64,
+    pub eta: i64,
+}
+
+#[event]
+pub struct UpdateCancelledEvent {
+    pub kind: QueuedUpdateKind,
+    pub new_value: u64,
+}
+
+#[event]
+pub struct TimelockDelayChangedEvent {
+    pub old_delay: i64,
+    pub new_delay: i64,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct XxUSDMintAuthorityAcceptedEvent {
+    pub mint: Pubkey,
+    pub mint_authority: Pubkey,
+}
+
+#[event]
+pub struct MintingLimitsUpdatedEvent {
+    pub global_limit: u64,
+    pub user_limit: u64,
+    pub epoch_limit: u64,
+    pub epoch_duration: i64,
+}
+
+#[event]
+pub struct CollateralRegisteredEvent {
+    pub mint: Pubkey,
+    pub lst_asset: Pubkey,
+    pub ltv_bps: u16,
+    pub deposit_cap: u64,
+}
+
+#[event]
+pub struct CollateralUpdatedEvent {
+    pub mint: Pubkey,
+    pub ltv_bps: u16,
+    pub deposit_cap: u64,
+    pub enabled: bool,
+}
+
+/// 以 PRICE_DECIMALS 位的單價計算抵押品數量的價值，並套用 LTV 折扣
+fn collateral_value(amount: u64, price: u64, decimals: u8, ltv_bps: u16) -> Result<u64> {
+    let value = (amount as u128)
+        .checked_mul(price as u128)
+        .and_then(|value| value.checked_div(10u128.pow(decimals as u32)))
+        .and_then(|value| value.checked_mul(ltv_bps as u128))
+        .and_then(|value| value.checked_div(BPS_DENOMINATOR as u128))
+        .ok_or(AssetManagerError::CalculationError)?;
+    u64::try_from(value).map_err(|_| AssetManagerError::CalculationError.into())
+}
+
+/// 計算 value × numerator / denominator，向下取整
+fn proportional(value: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let result = (value as u128)
+        .checked_mul(numerator as u128)
+        .and_then(|result| result.checked_div(denominator as u128))
+        .ok_or(AssetManagerError::CalculationError)?;
+    u64::try_from(result).map_err(|_| AssetManagerError::CalculationError.into())
+}
+
+/// 以資產年化收益支付商品價格所需的天數，限制在 MIN_LOCK_PERIOD 與 MAX_LOCK_PERIOD 之間
+fn lock_period_days(product_price: u64, asset_value: u64, apy: u64) -> Result<u64> {
+    require!(apy > 0, AssetManagerError::InvalidAPY);
+    let lock_period = (product_price as u128)
+        .checked_mul(DAYS_IN_YEAR as u128)
+        .and_then(|result| result.checked_mul(APY_PRECISION as u128))
+        .and_then(|result| {
+            let denominator = (asset_value as u128).checked_mul(apy as u128)?;
+            result.checked_div(denominator)
+        })
+        .ok_or(AssetManagerError::CalculationError)?;
+    Ok(lock_period.clamp(MIN_LOCK_PERIOD as u128, MAX_LOCK_PERIOD as u128) as u64)
+}
+
+fn validate_collateral_params(ltv_bps: u16, deposit_cap: u64) -> Result<()> {
+    require!(
+        ltv_bps > 0 && ltv_bps as u64 <= BPS_DENOMINATOR,
+        AssetManagerError::InvalidCollateralParams
+    );
+    require!(deposit_cap > 0, AssetManagerError::InvalidCollateralParams);
+    Ok(())
+}
+
+fn get_asset_price(lst_asset: &LstAsset, price_history: &PriceHistory) -> Result<u64> {
+    msg!("Fetching price from Oracle for asset: {}", lst_asset.mint);
+
+    let now = Clock::get()?.unix_timestamp;
+    let age = now
+        .checked_sub(lst_asset.last_update_timestamp)
+        .ok_or(AssetManagerError::CalculationError)?;
+    require!(age <= MAX_ORACLE_STALENESS, AssetManagerError::StaleOraclePrice);
+
+    // 將 Oracle 的 LST_PRICE_DECIMALS 位定點價格轉換為 PRICE_DECIMALS 位
+    let twap = price_history.twap(now, DEPOSIT_TWAP_WINDOW)?;
+    let scaled = twap / 10u64.pow(LST_PRICE_DECIMALS - PRICE_DECIMALS);
+    require!(scaled > 0, AssetManagerError::OracleError);
+
+    Ok(scaled)
+}
@@ -0,0 +1,1865 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    entrypoint::ProgramResult, instruction::{AccountMeta, Instruction}, program_option::COption, program_pack::{IsInitialized, Pack},
+    system_instruction, sysvar,
+};
+use anchor_lang::{system_program, InstructionData};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token::spl_token;
+use price_oracle::FeedGuards;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account as SolanaAccount, AccountSharedData},
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+
+const SECONDS_PER_DAY: i64 = 86400;
+// 固定起始時間（某日 01:00 UTC），讓「同一天」的判斷不受執行時間影響
+const START_TIME: i64 = 1_700_006_400 + 3600;
+const SLOTS_PER_SECOND: u64 = 2;
+
+const JUPSOL_DECIMALS: u8 = 9;
+const XXUSD_DECIMALS: u8 = 6;
+const JUPSOL_PRICE: i128 = 150 * 10i128.pow(18);
+const JUPSOL_APY: i128 = 762 * 10i128.pow(16);
+const SOL_PRICE: i128 = 140 * 10i128.pow(18);
+
+const JUPSOL_LTV_BPS: u16 = 8000;
+const DEPOSIT_CAP: u64 = 100_000_000_000;
+
+const GLOBAL_MINT_LIMIT: u64 = 10 * DEPOSIT_VALUE;
+const USER_MINT_LIMIT: u64 = 2 * DEPOSIT_VALUE;
+const EPOCH_MINT_LIMIT: u64 = 2 * DEPOSIT_VALUE;
+
+const DEPOSIT_AMOUNT: u64 = 50_000_000_000;
+// 50 jupSOL × 150 USD × 80% LTV，以 xxUSD 的 6 位小數表示
+const DEPOSIT_VALUE: u64 = 6_000_000_000;
+// 初始商品價格 1798 USD
+const LOCKED_BY_PRODUCT: u64 = 1_798_000_000;
+// 7.62% APY 下 6,000 USD 的收益需數年才能支付商品價格，鎖定天數取上限 365 天
+const LOCK_PERIOD_DAYS: u64 = 365;
+const DAILY_RELEASE: u64 = LOCKED_BY_PRODUCT / LOCK_PERIOD_DAYS;
+const REDEEM_AMOUNT: u64 = 1_500_000_000;
+// 15 jupSOL × 150 USD × 80% LTV = 1,800 USD，剛好足以支付一次商品價格
+const POSITION_DEPOSIT: u64 = 15_000_000_000;
+
+// Anchor 的 entry 要求帳戶切片與 AccountInfo 共用同一個生命週期，program-test 的 processor 沒有這個保證
+macro_rules! anchor_processor {
+    ($program:ident) => {{
+        fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+            let accounts = Box::leak(Box::new(accounts.to_vec()));
+            $program::entry(program_id, accounts, data)
+        }
+        processor!(process_instruction)
+    }};
+}
+
+fn feed_guards() -> FeedGuards {
+    FeedGuards {
+        max_slot_staleness: 150,
+        max_confidence_bps: 200,
+        min_samples: 1,
+    }
+}
+
+struct Lifecycle {
+    context: ProgramTestContext,
+    user: Keypair,
+    oracle: Keypair,
+    jupsol_mint: Keypair,
+    xxusd_mint: Keypair,
+}
+
+impl Lifecycle {
+    async fn new() -> Self {
+        let mut program_test = ProgramTest::default();
+        program_test.prefer_bpf(false);
+        program_test.add_program("access_control", access_control::ID, anchor_processor!(access_control));
+        program_test.add_program("asset_manager", asset_manager::ID, anchor_processor!(asset_manager));
+        program_test.add_program("hedging_strategy", hedging_strategy::ID, anchor_processor!(hedging_strategy));
+        program_test.add_program("lock_manager", lock_manager::ID, anchor_processor!(lock_manager));
+        program_test.add_program("mock_oracle", mock_oracle::ID, anchor_processor!(mock_oracle));
+        program_test.add_program("price_oracle", price_oracle::ID, anchor_processor!(price_oracle));
+        program_test.add_program("redemption_manager", redemption_manager::ID, anchor_processor!(redemption_manager));
+        program_test.add_program("xxusd_token", xxusd_token::ID, anchor_processor!(xxusd_token));
+
+        let user = Keypair::new();
+        program_test.add_account(
+            user.pubkey(),
+            SolanaAccount::new(10 * solana_sdk::native_token::LAMPORTS_PER_SOL, 0, &system_program::ID),
+        );
+
+        let mut lifecycle = Self {
+            context: program_test.start_with_context().await,
+            user,
+            oracle: Keypair::new(),
+            jupsol_mint: Keypair::new(),
+            xxusd_mint: Keypair::new(),
+        };
+        lifecycle.set_time(START_TIME).await;
+        lifecycle
+    }
+
+    fn authority(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    async fn now(&mut self) -> i64 {
+        self.context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    async fn set_time(&mut self, unix_timestamp: i64) {
+        let mut clock = self.context.banks_client.get_sysvar::<Clock>().await.unwrap();
+        let elapsed = (unix_timestamp - clock.unix_timestamp).max(0) as u64;
+        clock.slot += elapsed * SLOTS_PER_SECOND;
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    async fn warp(&mut self, seconds: i64) {
+        let now = self.now().await;
+        self.set_time(now + seconds).await;
+    }
+
+    async fn send(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> std::result::Result<(), BanksClientError> {
+        // 每筆交易換新的 blockhash，重送相同指令時才不會被視為重複交易
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers: Vec<&Keypair> = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction =
+            Transaction::new_signed_with_payer(instructions, Some(&self.context.payer.pubkey()), &all_signers, blockhash);
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    async fn account<T: AccountDeserialize>(&mut self, address: Pubkey) -> T {
+        let account = self.context.banks_client.get_account(address).await.unwrap().unwrap();
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn unpack<T: Pack + IsInitialized>(&mut self, address: Pubkey) -> T {
+        let account = self.context.banks_client.get_account(address).await.unwrap().unwrap();
+        T::unpack(&account.data).unwrap()
+    }
+
+    async fn token_balance(&mut self, address: Pubkey) -> u64 {
+        self.unpack::<spl_token::state::Account>(address).await.amount
+    }
+
+    async fn lamports(&mut self, address: Pubkey) -> u64 {
+        self.context.banks_client.get_balance(address).await.unwrap()
+    }
+
+    fn access_control_pda() -> Pubkey {
+        Pubkey::find_program_address(&[b"access_control"], &access_control::ID).0
+    }
+
+    fn mock_feed_pda(&self, label: &str) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"mock_feed", self.authority().as_ref(), label.as_bytes()],
+            &mock_oracle::ID,
+        )
+        .0
+    }
+
+    fn lst_asset_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"lst", self.oracle.pubkey().as_ref(), self.jupsol_mint.pubkey().as_ref()],
+            &price_oracle::ID,
+        )
+        .0
+    }
+
+    fn sol_feed_config_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"feed", self.oracle.pubkey().as_ref(), b"SOL"], &price_oracle::ID).0
+    }
+
+    fn price_history_pda(source: Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"history", source.as_ref()], &price_oracle::ID).0
+    }
+
+    fn vault_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"vault", self.jupsol_mint.pubkey().as_ref()], &asset_manager::ID).0
+    }
+
+    fn state_pda() -> Pubkey {
+        Pubkey::find_program_address(&[b"state"], &asset_manager::ID).0
+    }
+
+    fn mint_authority_pda(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Self::state_pda().as_ref()], &asset_manager::ID)
+    }
+
+    fn collateral_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"collateral", self.jupsol_mint.pubkey().as_ref()], &asset_manager::ID).0
+    }
+
+    fn user_deposit_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"user_deposit", self.user.pubkey().as_ref(), self.jupsol_mint.pubkey().as_ref()],
+            &asset_manager::ID,
+        )
+        .0
+    }
+
+    fn user_mint_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"user_mint", self.user.pubkey().as_ref()], &asset_manager::ID).0
+    }
+
+    fn lock_manager_pda() -> Pubkey {
+        Pubkey::find_program_address(&[b"lock_manager"], &lock_manager::ID).0
+    }
+
+    fn lock_index_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"lock_index", self.user.pubkey().as_ref()], &lock_manager::ID).0
+    }
+
+    fn lock_record_pda(&self, lock_id: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"lock_record", self.user.pubkey().as_ref(), &lock_id.to_le_bytes()],
+            &lock_manager::ID,
+        )
+        .0
+    }
+
+    fn system_state_pda() -> Pubkey {
+        Pubkey::find_program_address(&[b"system_state"], &redemption_manager::ID).0
+    }
+
+    fn redemption_manager_pda() -> Pubkey {
+        Pubkey::find_program_address(&[b"redemption_manager"], &redemption_manager::ID).0
+    }
+
+    fn redemption_request_pda(&self, lock_id: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"redemption_request", self.user.pubkey().as_ref(), &lock_id.to_le_bytes()],
+            &redemption_manager::ID,
+        )
+        .0
+    }
+
+    fn lock_redemption_pda(&self, lock_id: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"lock_redemption", self.user.pubkey().as_ref(), &lock_id.to_le_bytes()],
+            &redemption_manager::ID,
+        )
+        .0
+    }
+
+    fn user_ata(&self, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address(&self.user.pubkey(), mint)
+    }
+
+    fn create_ata_ix(&self, owner: &Pubkey, mint: &Pubkey) -> Instruction {
+        associated_token::spl_associated_token_account::instruction::create_associated_token_account(
+            &self.authority(),
+            owner,
+            mint,
+            &spl_token::ID,
+        )
+    }
+
+    async fn set_mock_feed(&mut self, label: &str, value: i128) {
+        let ix = Instruction {
+            program_id: mock_oracle::ID,
+            accounts: mock_oracle::accounts::SetMockFeed {
+                mock_feed: self.mock_feed_pda(label),
+                authority: self.authority(),
+            }
+            .to_account_metas(None),
+            data: mock_oracle::instruction::SetMockFeed {
+                label: label.to_string(),
+                value,
+                std_dev: 0,
+                samples: 1,
+            }
+            .data(),
+        };
+        self.send(&[ix], &[]).await.unwrap();
+    }
+
+    fn refresh_jupsol_ix(&self) -> Instruction {
+        let lst_asset = self.lst_asset_pda();
+        Instruction {
+            program_id: price_oracle::ID,
+            accounts: price_oracle::accounts::RefreshLstAsset {
+                lst_asset,
+                price_feed: self.mock_feed_pda("jupsol"),
+                apy_feed: self.mock_feed_pda("jupsol_apy"),
+                price_history: Self::price_history_pda(lst_asset),
+            }
+            .to_account_metas(None),
+            data: price_oracle::instruction::RefreshLstAsset {}.data(),
+        }
+    }
+
+    async fn refresh_jupsol(&mut self) {
+        self.set_mock_feed("jupsol", JUPSOL_PRICE).await;
+        self.set_mock_feed("jupsol_apy", JUPSOL_APY).await;
+        self.send(&[self.refresh_jupsol_ix()], &[]).await.unwrap();
+    }
+
+    fn get_sol_price_ix(&self) -> Instruction {
+        let feed_config = self.sol_feed_config_pda();
+        Instruction {
+            program_id: price_oracle::ID,
+            accounts: price_oracle::accounts::GetPrice {
+                oracle_account: self.oracle.pubkey(),
+                feed_config,
+                feed: self.mock_feed_pda("sol"),
+                price_history: Self::price_history_pda(feed_config),
+            }
+            .to_account_metas(None),
+            data: price_oracle::instruction::GetPrice { asset: "SOL".to_string() }.data(),
+        }
+    }
+
+    fn push_manual_price_ix(&self, authority: Pubkey, price: u64) -> Instruction {
+        Instruction {
+            program_id: price_oracle::ID,
+            accounts: price_oracle::accounts::PushManualPrice {
+                oracle_account: self.oracle.pubkey(),
+                feed_config: self.sol_feed_config_pda(),
+                authority,
+            }
+            .to_account_metas(None),
+            data: price_oracle::instruction::PushManualPrice { price }.data(),
+        }
+    }
+
+    async fn create_mint(&mut self, mint: &Keypair, decimals: u8) {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let authority = self.authority();
+        let create = system_instruction::create_account(
+            &authority,
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::ID,
+        );
+        let initialize =
+            spl_token::instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), &authority, None, decimals).unwrap();
+        let mint = mint.insecure_clone();
+        self.send(&[create, initialize], &[&mint]).await.unwrap();
+    }
+
+    /// 部署並初始化各程式：存取控制、Oracle（模擬 feed）、資產管理、xxUSD 與贖回系統
+    async fn bootstrap(&mut self) {
+        let authority = self.authority();
+
+        let ix = Instruction {
+            program_id: access_control::ID,
+            accounts: access_control::accounts::Initialize {
+                access_control: Self::access_control_pda(),
+                admin: authority,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: access_control::instruction::Initialize {
+                bump: Pubkey::find_program_address(&[b"access_control"], &access_control::ID).1,
+            }
+            .data(),
+        };
+        self.send(&[ix], &[]).await.unwrap();
+
+        let jupsol_mint = self.jupsol_mint.insecure_clone();
+        self.create_mint(&jupsol_mint, JUPSOL_DECIMALS).await;
+
+        let oracle = self.oracle.insecure_clone();
+        let mut instructions = vec![Instruction {
+            program_id: price_oracle::ID,
+            accounts: price_oracle::accounts::Initialize {
+                oracle_account: oracle.pubkey(),
+                authority,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: price_oracle::instruction::Initialize {}.data(),
+        }];
+        for label in ["jupsol", "jupsol_apy", "sol"] {
+            instructions.push(Instruction {
+                program_id: mock_oracle::ID,
+                accounts: mock_oracle::accounts::InitMockFeed {
+                    mock_feed: self.mock_feed_pda(label),
+                    authority,
+                    system_program: system_program::ID,
+                }
+                .to_account_metas(None),
+                data: mock_oracle::instruction::InitMockFeed { label: label.to_string() }.data(),
+            });
+        }
+        self.send(&instructions, &[&oracle]).await.unwrap();
+
+        let lst_asset = self.lst_asset_pda();
+        let sol_feed_config = self.sol_feed_config_pda();
+        let register = [
+            Instruction {
+                program_id: price_oracle::ID,
+                accounts: price_oracle::accounts::AddLstAsset {
+                    oracle_account: oracle.pubkey(),
+                    lst_asset,
+                    price_history: Self::price_history_pda(lst_asset),
+                    mint: jupsol_mint.pubkey(),
+                    price_feed: self.mock_feed_pda("jupsol"),
+                    apy_feed: self.mock_feed_pda("jupsol_apy"),
+                    authority,
+                    system_program: system_program::ID,
+                }
+                .to_account_metas(None),
+                data: price_oracle::instruction::AddLstAsset {
+                    symbol: "JUPSOL".to_string(),
+                    max_staleness: 60,
+                    guards: feed_guards(),
+                }
+                .data(),
+            },
+            Instruction {
+                program_id: price_oracle::ID,
+                accounts: price_oracle::accounts::AddFeed {
+                    oracle_account: oracle.pubkey(),
+                    feed_config: sol_feed_config,
+                    price_history: Self::price_history_pda(sol_feed_config),
+                    feed: self.mock_feed_pda("sol"),
+                    authority,
+                    system_program: system_program::ID,
+                }
+                .to_account_metas(None),
+                data: price_oracle::instruction::AddFeed {
+                    symbol: "SOL".to_string(),
+                    max_staleness: 60,
+                    decimals: 0,
+                    guards: feed_guards(),
+                }
+                .data(),
+            },
+        ];
+        self.send(&register, &[]).await.unwrap();
+        self.set_mock_feed("sol", SOL_PRICE).await;
+
+        // xxUSD mint 由 xxusd_token 建立，初始化時鑄幣權仍在部署者手上
+        let xxusd_mint = self.xxusd_mint.insecure_clone();
+        let ix = Instruction {
+            program_id: xxusd_token::ID,
+            accounts: xxusd_token::accounts::Initialize {
+                mint: xxusd_mint.pubkey(),
+                authority,
+                system_program: system_program::ID,
+                token_program: spl_token::ID,
+                rent: sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: xxusd_token::instruction::Initialize {
+                decimals: XXUSD_DECIMALS,
+                freeze_authority: None,
+            }
+            .data(),
+        };
+        self.send(&[ix], &[&xxusd_mint]).await.unwrap();
+
+        let (_, nonce) = self.mint_authority_pda();
+        self.send(&[self.initialize_ix(Self::state_pda(), authority)], &[]).await.unwrap();
+        let program_state: asset_manager::ProgramState = self.account(Self::state_pda()).await;
+        assert_eq!(program_state.nonce, nonce);
+        assert_eq!(program_state.xxusd_mint, xxusd_mint.pubkey());
+
+        let ix = self.accept_mint_authority_ix();
+        self.send(&[ix], &[]).await.unwrap();
+
+        let ix = self.set_minting_limits_ix(GLOBAL_MINT_LIMIT, USER_MINT_LIMIT, EPOCH_MINT_LIMIT, SECONDS_PER_DAY);
+        self.send(&[ix], &[]).await.unwrap();
+
+        let ix = self.add_collateral_ix(&jupsol_mint.pubkey(), authority);
+        self.send(&[ix], &[]).await.unwrap();
+
+        let user = self.user.pubkey();
+        let accounts = [
+            self.create_ata_ix(&user, &jupsol_mint.pubkey()),
+            self.create_ata_ix(&user, &xxusd_mint.pubkey()),
+            self.create_ata_ix(&Self::lock_manager_pda(), &xxusd_mint.pubkey()),
+            self.create_ata_ix(&Self::redemption_manager_pda(), &xxusd_mint.pubkey()),
+            spl_token::instruction::mint_to(
+                &spl_token::ID,
+                &jupsol_mint.pubkey(),
+                &self.user_ata(&jupsol_mint.pubkey()),
+                &authority,
+                &[],
+                DEPOSIT_AMOUNT,
+            )
+            .unwrap(),
+        ];
+        self.send(&accounts, &[]).await.unwrap();
+
+        // 贖回系統以 PDA 作為 SOL 出金來源，注資時建立為程式擁有的帳戶
+        let ix = Instruction {
+            program_id: redemption_manager::ID,
+            accounts: redemption_manager::accounts::FundRedemptionPool {
+                funder: authority,
+                redemption_manager: Self::redemption_manager_pda(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: redemption_manager::instruction::FundRedemptionPool {
+                amount: 100 * solana_sdk::native_token::LAMPORTS_PER_SOL,
+            }
+            .data(),
+        };
+        self.send(&[ix], &[]).await.unwrap();
+        let ix = Instruction {
+            program_id: redemption_manager::ID,
+            accounts: redemption_manager::accounts::InitializeSystemState {
+                system_state: Self::system_state_pda(),
+                authority,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: redemption_manager::instruction::InitializeSystemState { oracle: oracle.pubkey() }.data(),
+        };
+        self.send(&[ix], &[]).await.unwrap();
+    }
+
+    fn initialize_ix(&self, state: Pubkey, authority: Pubkey) -> Instruction {
+        let xxusd_mint = self.xxusd_mint.pubkey();
+        let mint_authority = Pubkey::find_program_address(&[state.as_ref()], &asset_manager::ID).0;
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::Initialize {
+                state,
+                xxusd_mint,
+                mint_authority,
+                xxusd_vault: get_associated_token_address(&mint_authority, &xxusd_mint),
+                access_control: Self::access_control_pda(),
+                authority,
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::Initialize { oracle: self.oracle.pubkey() }.data(),
+        }
+    }
+
+    fn add_collateral_ix(&self, mint: &Pubkey, authority: Pubkey) -> Instruction {
+        let lst_asset = Pubkey::find_program_address(
+            &[b"lst", self.oracle.pubkey().as_ref(), mint.as_ref()],
+            &price_oracle::ID,
+        )
+        .0;
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::AddCollateral {
+                state: Self::state_pda(),
+                asset_mint: *mint,
+                collateral: Pubkey::find_program_address(&[b"collateral", mint.as_ref()], &asset_manager::ID).0,
+                vault_asset_account: Pubkey::find_program_address(&[b"vault", mint.as_ref()], &asset_manager::ID).0,
+                lst_asset,
+                authority,
+                payer: authority,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::AddCollateral {
+                ltv_bps: JUPSOL_LTV_BPS,
+                deposit_cap: DEPOSIT_CAP,
+            }
+            .data(),
+        }
+    }
+
+    fn deposit_ix(&self, amount: u64) -> Instruction {
+        let lst_asset = self.lst_asset_pda();
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::DepositAsset {
+                user: self.user.pubkey(),
+                user_asset_account: self.user_ata(&self.jupsol_mint.pubkey()),
+                asset_mint: self.jupsol_mint.pubkey(),
+                collateral: self.collateral_pda(),
+                vault_asset_account: self.vault_pda(),
+                user_deposit: self.user_deposit_pda(),
+                state: Self::state_pda(),
+                lst_asset,
+                price_history: Self::price_history_pda(lst_asset),
+                access_control: Self::access_control_pda(),
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::DepositAsset { amount }.data(),
+        }
+    }
+
+    /// `lock_id` 為這次鑄造將開立的鎖倉部位編號
+    fn mint_ix(&self, lock_id: u64) -> Instruction {
+        let lst_asset = self.lst_asset_pda();
+        let (mint_authority, _) = self.mint_authority_pda();
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::MintAndDistributeXxUSD {
+                user: self.user.pubkey(),
+                xxusd_mint: self.xxusd_mint.pubkey(),
+                xxusd_vault: get_associated_token_address(&mint_authority, &self.xxusd_mint.pubkey()),
+                user_xxusd_account: self.user_ata(&self.xxusd_mint.pubkey()),
+                mint_authority,
+                vault_authority: mint_authority,
+                user_deposit: self.user_deposit_pda(),
+                user_mint: self.user_mint_pda(),
+                state: Self::state_pda(),
+                collateral: self.collateral_pda(),
+                lst_asset,
+                price_history: Self::price_history_pda(lst_asset),
+                access_control: Self::access_control_pda(),
+                lock_vault: get_associated_token_address(&Self::lock_manager_pda(), &self.xxusd_mint.pubkey()),
+                lock_manager: Self::lock_manager_pda(),
+                lock_index: self.lock_index_pda(),
+                lock_record: self.lock_record_pda(lock_id),
+                lock_manager_program: lock_manager::ID,
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::MintAndDistributeXxusd {}.data(),
+        }
+    }
+
+    fn withdraw_ix(&self, repay_amount: u64) -> Instruction {
+        let lst_asset = self.lst_asset_pda();
+        let (mint_authority, _) = self.mint_authority_pda();
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::WithdrawAsset {
+                user: self.user.pubkey(),
+                user_asset_account: self.user_ata(&self.jupsol_mint.pubkey()),
+                asset_mint: self.jupsol_mint.pubkey(),
+                collateral: self.collateral_pda(),
+                vault_asset_account: self.vault_pda(),
+                user_deposit: self.user_deposit_pda(),
+                user_xxusd_account: self.user_ata(&self.xxusd_mint.pubkey()),
+                xxusd_mint: self.xxusd_mint.pubkey(),
+                mint_authority,
+                user_mint: self.user_mint_pda(),
+                state: Self::state_pda(),
+                lst_asset,
+                price_history: Self::price_history_pda(lst_asset),
+                access_control: Self::access_control_pda(),
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::WithdrawAsset { repay_amount }.data(),
+        }
+    }
+
+    fn release_ix(&self, lock_id: u64) -> Instruction {
+        self.release_mint_ix(lock_id, &self.xxusd_mint.pubkey())
+    }
+
+    fn release_mint_ix(&self, lock_id: u64, mint: &Pubkey) -> Instruction {
+        let lock_manager = Self::lock_manager_pda();
+        Instruction {
+            program_id: lock_manager::ID,
+            accounts: lock_manager::accounts::ReleaseDailyXxUSD {
+                user: self.user.pubkey(),
+                user_token_account: self.user_ata(mint),
+                xxusd_mint: *mint,
+                lock_vault: get_associated_token_address(&lock_manager, mint),
+                lock_manager,
+                lock_record: self.lock_record_pda(lock_id),
+                lock_index: self.lock_index_pda(),
+                access_control: Self::access_control_pda(),
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: lock_manager::instruction::ReleaseDailyXxusd { lock_id }.data(),
+        }
+    }
+
+    /// 用戶直接透過 lock_manager 鎖倉任意代幣，lock_id 為其索引中的下一個編號
+    fn lock_ix(&self, mint: &Pubkey, lock_id: u64, amount: u64, daily_release: u64) -> Instruction {
+        let lock_manager = Self::lock_manager_pda();
+        Instruction {
+            program_id: lock_manager::ID,
+            accounts: lock_manager::accounts::LockXxUSD {
+                user: self.user.pubkey(),
+                user_token_account: self.user_ata(mint),
+                xxusd_mint: *mint,
+                lock_vault: get_associated_token_address(&lock_manager, mint),
+                lock_manager,
+                lock_index: self.lock_index_pda(),
+                lock_record: self.lock_record_pda(lock_id),
+                asset_manager: asset_manager::ID,
+                access_control: Self::access_control_pda(),
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                rent: solana_sdk::sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: lock_manager::instruction::LockXxusd {
+                amount,
+                lock_period: LOCK_PERIOD_DAYS,
+                daily_release,
+            }
+            .data(),
+        }
+    }
+
+    fn initiate_redeem_ix(&self, lock_id: u64, amount: u64) -> Instruction {
+        Instruction {
+            program_id: redemption_manager::ID,
+            accounts: redemption_manager::accounts::InitiateRedeem {
+                user: self.user.pubkey(),
+                user_token_account: self.user_ata(&self.xxusd_mint.pubkey()),
+                redemption_vault: get_associated_token_address(
+                    &Self::redemption_manager_pda(),
+                    &self.xxusd_mint.pubkey(),
+                ),
+                asset_manager_state: Self::state_pda(),
+                xxusd_mint: self.xxusd_mint.pubkey(),
+                redemption_manager: Self::redemption_manager_pda(),
+                lock_record: self.lock_record_pda(lock_id),
+                redemption_request: self.redemption_request_pda(lock_id),
+                lock_redemption: self.lock_redemption_pda(lock_id),
+                system_state: Self::system_state_pda(),
+                access_control: Self::access_control_pda(),
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: redemption_manager::instruction::InitiateRedeem { lock_id, amount }.data(),
+        }
+    }
+
+    fn execute_redeem_ix(&self, lock_id: u64) -> Instruction {
+        let redemption_manager = Self::redemption_manager_pda();
+        Instruction {
+            program_id: redemption_manager::ID,
+            accounts: redemption_manager::accounts::ExecuteRedeem {
+                user: self.user.pubkey(),
+                redemption_vault: get_associated_token_address(&redemption_manager, &self.xxusd_mint.pubkey()),
+                redemption_request: self.redemption_request_pda(lock_id),
+                system_state: Self::system_state_pda(),
+                access_control: Self::access_control_pda(),
+                sol_price_history: Self::price_history_pda(self.sol_feed_config_pda()),
+                oracle_account: self.oracle.pubkey(),
+                sol_feed_config: self.sol_feed_config_pda(),
+                sol_feed: self.mock_feed_pda("sol"),
+                price_oracle_program: price_oracle::ID,
+                xxusd_mint: self.xxusd_mint.pubkey(),
+                redemption_manager,
+                asset_manager_state: Self::state_pda(),
+                xxusd_mint_authority: self.mint_authority_pda().0,
+                user_mint: self.user_mint_pda(),
+                asset_manager_program: asset_manager::ID,
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: redemption_manager::instruction::ExecuteRedeem { lock_id }.data(),
+        }
+    }
+
+    fn close_lock_ix(&self, lock_id: u64) -> Instruction {
+        Instruction {
+            program_id: lock_manager::ID,
+            accounts: lock_manager::accounts::CloseLockRecord {
+                user: self.user.pubkey(),
+                lock_record: self.lock_record_pda(lock_id),
+                xxusd_mint: self.xxusd_mint.pubkey(),
+                lock_index: self.lock_index_pda(),
+            }
+            .to_account_metas(None),
+            data: lock_manager::instruction::CloseLockRecord { lock_id }.data(),
+        }
+    }
+
+    fn close_redemption_ix(&self, lock_id: u64) -> Instruction {
+        Instruction {
+            program_id: redemption_manager::ID,
+            accounts: redemption_manager::accounts::CloseRedemptionRequest {
+                user: self.user.pubkey(),
+                redemption_request: self.redemption_request_pda(lock_id),
+            }
+            .to_account_metas(None),
+            data: redemption_manager::instruction::CloseRedemptionRequest { lock_id }.data(),
+        }
+    }
+
+    fn update_collateral_ix(&self, ltv_bps: u16, deposit_cap: u64, enabled: bool) -> Instruction {
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::UpdateCollateral {
+                state: Self::state_pda(),
+                collateral: self.collateral_pda(),
+                authority: self.authority(),
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::UpdateCollateral { ltv_bps, deposit_cap, enabled }.data(),
+        }
+    }
+
+    fn accept_mint_authority_ix(&self) -> Instruction {
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::AcceptXxUSDMintAuthority {
+                state: Self::state_pda(),
+                xxusd_mint: self.xxusd_mint.pubkey(),
+                mint_authority: self.mint_authority_pda().0,
+                authority: self.authority(),
+                xxusd_token_program: xxusd_token::ID,
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::AcceptXxusdMintAuthority {}.data(),
+        }
+    }
+
+    fn set_minting_limits_ix(&self, global_limit: u64, user_limit: u64, epoch_limit: u64, epoch_duration: i64) -> Instruction {
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::SetMintingLimits {
+                state: Self::state_pda(),
+                access_control: Self::access_control_pda(),
+                authority: self.authority(),
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::SetMintingLimits {
+                global_limit,
+                user_limit,
+                epoch_limit,
+                epoch_duration,
+            }
+            .data(),
+        }
+    }
+
+    fn queue_timelock_delay_ix(&self, new_delay: i64) -> Instruction {
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::QueueUpdate {
+                state: Self::state_pda(),
+                authority: self.authority(),
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::QueueTimelockDelayUpdate { new_delay }.data(),
+        }
+    }
+
+    fn execute_queued_update_ix(&self, kind: asset_manager::QueuedUpdateKind) -> Instruction {
+        Instruction {
+            program_id: asset_manager::ID,
+            accounts: asset_manager::accounts::ExecuteQueuedUpdate {
+                state: Self::state_pda(),
+                executor: self.authority(),
+            }
+            .to_account_metas(None),
+            data: asset_manager::instruction::ExecuteQueuedUpdate { kind }.data(),
+        }
+    }
+
+    fn grant_role_ix(&self, role: access_control::Role, account: Pubkey) -> Instruction {
+        Instruction {
+            program_id: access_control::ID,
+            accounts: access_control::accounts::SetPermissions {
+                access_control: Self::access_control_pda(),
+                admin: self.authority(),
+            }
+            .to_account_metas(None),
+            data: access_control::instruction::GrantRole { role, account }.data(),
+        }
+    }
+
+    fn emergency_stop_ix(admin: Pubkey) -> Instruction {
+        Instruction {
+            program_id: access_control::ID,
+            accounts: access_control::accounts::EmergencyStop {
+                access_control: Self::access_control_pda(),
+                admin,
+            }
+            .to_account_metas(None),
+            data: access_control::instruction::EmergencyStop {}.data(),
+        }
+    }
+
+    fn resume_ix(admin: Pubkey) -> Instruction {
+        Instruction {
+            program_id: access_control::ID,
+            accounts: access_control::accounts::EmergencyStop {
+                access_control: Self::access_control_pda(),
+                admin,
+            }
+            .to_account_metas(None),
+            data: access_control::instruction::Resume {}.data(),
+        }
+    }
+
+    fn multisig_pda() -> Pubkey {
+        Pubkey::find_program_address(&[b"multisig"], &access_control::ID).0
+    }
+
+    fn proposal_pda(id: u64) -> Pubkey {
+        Pubkey::find_program_address(&[b"proposal", &id.to_le_bytes()], &access_control::ID).0
+    }
+
+    fn create_multisig_ix(&self, signers: Vec<Pubkey>, threshold: u8) -> Instruction {
+        Instruction {
+            program_id: access_control::ID,
+            accounts: access_control::accounts::CreateMultisig {
+                access_control: Self::access_control_pda(),
+                multisig: Self::multisig_pda(),
+                asset_manager_state: Self::state_pda(),
+                asset_manager_program: asset_manager::ID,
+                admin: self.authority(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: access_control::instruction::CreateMultisig { signers, threshold }.data(),
+        }
+    }
+
+    fn create_proposal_ix(&self, id: u64, action: access_control::ProposalAction) -> Instruction {
+        Instruction {
+            program_id: access_control::ID,
+            accounts: access_control::accounts::CreateProposal {
+                multisig: Self::multisig_pda(),
+                proposal: Self::proposal_pda(id),
+                proposer: self.authority(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: access_control::instruction::CreateProposal { action }.data(),
+        }
+    }
+
+    fn approve_ix(id: u64, approver: Pubkey) -> Instruction {
+        Instruction {
+            program_id: access_control::ID,
+            accounts: access_control::accounts::Approve {
+                multisig: Self::multisig_pda(),
+                proposal: Self::proposal_pda(id),
+                approver,
+            }
+            .to_account_metas(None),
+            data: access_control::instruction::Approve {}.data(),
+        }
+    }
+
+    /// `target_accounts` 依序接在 remaining_accounts，供 asset_manager 指令使用
+    fn execute_ix(&self, id: u64, target_accounts: Vec<AccountMeta>) -> Instruction {
+        let mut accounts = access_control::accounts::Execute {
+            access_control: Self::access_control_pda(),
+            multisig: Self::multisig_pda(),
+            proposal: Self::proposal_pda(id),
+            executor: self.authority(),
+        }
+        .to_account_metas(None);
+        accounts.extend(target_accounts);
+        Instruction {
+            program_id: access_control::ID,
+            accounts,
+            data: access_control::instruction::Execute {}.data(),
+        }
+    }
+
+    /// 追加鑄造 jupSOL 給用戶並存入
+    async fn deposit_more(&mut self, amount: u64) {
+        let user = self.user.insecure_clone();
+        let jupsol_mint = self.jupsol_mint.pubkey();
+        let mint_to = spl_token::instruction::mint_to(
+            &spl_token::ID,
+            &jupsol_mint,
+            &self.user_ata(&jupsol_mint),
+            &self.authority(),
+            &[],
+            amount,
+        )
+        .unwrap();
+        self.send(&[mint_to], &[]).await.unwrap();
+        self.send(&[self.deposit_ix(amount)], &[&user]).await.unwrap();
+    }
+
+    /// 部署後累積一段 jupSOL 價格紀錄，讓存款可以用 TWAP 計價
+    async fn bootstrap_with_prices(&mut self) {
+        self.bootstrap().await;
+        self.refresh_jupsol().await;
+        self.warp(700).await;
+        self.refresh_jupsol().await;
+    }
+
+    async fn deposit_and_mint(&mut self) {
+        self.bootstrap_with_prices().await;
+
+        let user = self.user.insecure_clone();
+        self.send(&[self.deposit_ix(DEPOSIT_AMOUNT)], &[&user]).await.unwrap();
+        self.send(&[self.mint_ix(0)], &[&user]).await.unwrap();
+    }
+
+    /// 存入 jupSOL 並鑄造 xxUSD，商品價格部分由鑄造一併鎖倉，回傳鎖定開始時間
+    async fn deposit_mint_and_lock(&mut self) -> i64 {
+        self.deposit_and_mint().await;
+
+        let lock_record: lock_manager::LockRecord = self.account(self.lock_record_pda(0)).await;
+        lock_record.start_time
+    }
+}
+
+fn assert_custom_error(result: std::result::Result<(), BanksClientError>, expected: u32) {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => assert_eq!(code, expected),
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn deposit_to_redemption_lifecycle() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+
+    let jupsol_mint = lifecycle.jupsol_mint.pubkey();
+    let xxusd_mint = lifecycle.xxusd_mint.pubkey();
+    let user_xxusd = lifecycle.user_ata(&xxusd_mint);
+    let lock_vault = get_associated_token_address(&Lifecycle::lock_manager_pda(), &xxusd_mint);
+    let redemption_manager = Lifecycle::redemption_manager_pda();
+    let redemption_vault = get_associated_token_address(&redemption_manager, &xxusd_mint);
+    let (mint_authority, _) = lifecycle.mint_authority_pda();
+    let product_vault = get_associated_token_address(&mint_authority, &xxusd_mint);
+
+    // 存款：jupSOL 全數進入金庫，並以 TWAP 記錄 USD 價值
+    assert_eq!(lifecycle.token_balance(lifecycle.user_ata(&jupsol_mint)).await, 0);
+    assert_eq!(lifecycle.token_balance(lifecycle.vault_pda()).await, DEPOSIT_AMOUNT);
+    let user_deposit: asset_manager::UserDeposit = lifecycle.account(lifecycle.user_deposit_pda()).await;
+    assert_eq!(user_deposit.amount, DEPOSIT_VALUE);
+    assert_eq!(user_deposit.collateral_amount, DEPOSIT_AMOUNT);
+    let collateral: asset_manager::CollateralConfig = lifecycle.account(lifecycle.collateral_pda()).await;
+    assert_eq!(collateral.total_deposited, DEPOSIT_AMOUNT);
+    assert_eq!(user_deposit.xxusd_amount, DEPOSIT_VALUE);
+
+    // 鑄造即鎖倉：商品價格部分由金庫轉入 lock_manager，其餘發給用戶
+    assert_eq!(lifecycle.token_balance(product_vault).await, 0);
+    assert_eq!(lifecycle.token_balance(lock_vault).await, LOCKED_BY_PRODUCT);
+    let unlocked = DEPOSIT_VALUE - LOCKED_BY_PRODUCT;
+    assert_eq!(lifecycle.token_balance(user_xxusd).await, unlocked);
+    let lock_record: lock_manager::LockRecord = lifecycle.account(lifecycle.lock_record_pda(0)).await;
+    assert_eq!(lock_record.owner, lifecycle.user.pubkey());
+    assert_eq!(lock_record.lock_period, LOCK_PERIOD_DAYS);
+    assert_eq!(lock_record.daily_release, DAILY_RELEASE);
+
+    // 隔天可釋放一日額度，同日再次釋放會被拒絕
+    lifecycle.set_time(lock_start + SECONDS_PER_DAY).await;
+    let user = lifecycle.user.insecure_clone();
+    lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.token_balance(user_xxusd).await, unlocked + DAILY_RELEASE);
+    assert_eq!(lifecycle.token_balance(lock_vault).await, LOCKED_BY_PRODUCT - DAILY_RELEASE);
+    let lock_record: lock_manager::LockRecord = lifecycle.account(lifecycle.lock_record_pda(0)).await;
+    assert_eq!(lock_record.amount, LOCKED_BY_PRODUCT - DAILY_RELEASE);
+
+    // 鎖定期結束後進入贖回窗口
+    lifecycle.set_time(lock_start + LOCK_PERIOD_DAYS as i64 * SECONDS_PER_DAY + 3600).await;
+
+    // 贖回數量以該部位仍鎖定的餘額為上限
+    let result = lifecycle.send(&[lifecycle.initiate_redeem_ix(0, LOCKED_BY_PRODUCT)], &[&user]).await;
+    assert_custom_error(result, redemption_manager::RedemptionError::ExceedsLockedAmount.into());
+    lifecycle.send(&[lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.token_balance(user_xxusd).await, unlocked + DAILY_RELEASE - REDEEM_AMOUNT);
+    assert_eq!(lifecycle.token_balance(redemption_vault).await, REDEEM_AMOUNT);
+
+    let user_lamports = lifecycle.lamports(user.pubkey()).await;
+    let pool_lamports = lifecycle.lamports(redemption_manager).await;
+    lifecycle.set_mock_feed("sol", SOL_PRICE).await;
+    lifecycle.send(&[lifecycle.execute_redeem_ix(0)], &[&user]).await.unwrap();
+
+    // 贖回：金庫中的 xxUSD 被銷毀，按 Oracle 的 SOL 價格（1,500 USD ÷ 140 USD）由贖回 PDA 轉給用戶
+    let sol_amount = 10_714_285_714;
+    assert_eq!(lifecycle.token_balance(redemption_vault).await, 0);
+    assert_eq!(lifecycle.lamports(user.pubkey()).await, user_lamports + sol_amount);
+    assert_eq!(lifecycle.lamports(redemption_manager).await, pool_lamports - sol_amount);
+    let mint_account = lifecycle.context.banks_client.get_account(xxusd_mint).await.unwrap().unwrap();
+    let supply = spl_token::state::Mint::unpack(&mint_account.data).unwrap().supply;
+    assert_eq!(supply, DEPOSIT_VALUE - REDEEM_AMOUNT);
+
+    // 銷毀同步反映在 asset_manager 的流通量與用戶鑄造紀錄
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.outstanding_supply, DEPOSIT_VALUE - REDEEM_AMOUNT);
+    let user_mint: asset_manager::UserMintRecord = lifecycle.account(lifecycle.user_mint_pda()).await;
+    assert_eq!(user_mint.outstanding, DEPOSIT_VALUE - REDEEM_AMOUNT);
+
+    let request: redemption_manager::RedemptionRequest = lifecycle.account(lifecycle.redemption_request_pda(0)).await;
+    assert!(request.is_processed);
+    let result = lifecycle.send(&[lifecycle.execute_redeem_ix(0)], &[&user]).await;
+    assert_custom_error(result, redemption_manager::RedemptionError::AlreadyProcessed.into());
+
+    // 最後一次釋放領回剩餘的鎖倉餘額，包含每日額度整除後的零頭，金庫不留任何 xxUSD
+    assert_ne!(LOCKED_BY_PRODUCT % LOCK_PERIOD_DAYS, 0);
+    let user_balance = lifecycle.token_balance(user_xxusd).await;
+    lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await.unwrap();
+    assert_eq!(
+        lifecycle.token_balance(user_xxusd).await,
+        user_balance + LOCKED_BY_PRODUCT - DAILY_RELEASE
+    );
+    assert_eq!(lifecycle.token_balance(lock_vault).await, 0);
+    let lock_record: lock_manager::LockRecord = lifecycle.account(lifecycle.lock_record_pda(0)).await;
+    assert_eq!(lock_record.amount, 0);
+}
+
+#[tokio::test]
+async fn refresh_rejects_feed_not_owned_by_a_trusted_oracle_program() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+    lifecycle.set_mock_feed("jupsol", JUPSOL_PRICE).await;
+    lifecycle.set_mock_feed("jupsol_apy", JUPSOL_APY).await;
+
+    // 版面與資料完全相同，但由其他程式擁有的帳戶不能當作價格來源
+    let feed = lifecycle.mock_feed_pda("jupsol");
+    let mut forged = lifecycle.context.banks_client.get_account(feed).await.unwrap().unwrap();
+    forged.owner = price_oracle::ID;
+    lifecycle.context.set_account(&feed, &AccountSharedData::from(forged));
+
+    let result = lifecycle.send(&[lifecycle.refresh_jupsol_ix()], &[]).await;
+    assert_custom_error(result, price_oracle::PriceOracleError::UntrustedFeedOwner.into());
+}
+
+#[tokio::test]
+async fn feed_price_is_cached_per_feed_until_the_feed_is_updated() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+    let feed_config = lifecycle.sol_feed_config_pda();
+
+    lifecycle.send(&[lifecycle.get_sol_price_ix()], &[]).await.unwrap();
+    let config: price_oracle::FeedConfig = lifecycle.account(feed_config).await;
+    assert_eq!(config.cached_price, 140);
+    assert_eq!(config.last_update_timestamp, lifecycle.now().await);
+
+    // 快取未過期前不重新讀取 feed
+    lifecycle.set_mock_feed("sol", 150 * 10i128.pow(18)).await;
+    lifecycle.send(&[lifecycle.get_sol_price_ix()], &[]).await.unwrap();
+    let config: price_oracle::FeedConfig = lifecycle.account(feed_config).await;
+    assert_eq!(config.cached_price, 140);
+
+    // 更新 feed 設定會清除快取，之後以新的小數位數重新讀取
+    let update = Instruction {
+        program_id: price_oracle::ID,
+        accounts: price_oracle::accounts::UpdateFeed {
+            oracle_account: lifecycle.oracle.pubkey(),
+            feed_config,
+            feed: lifecycle.mock_feed_pda("sol"),
+            authority: lifecycle.authority(),
+        }
+        .to_account_metas(None),
+        data: price_oracle::instruction::UpdateFeed { max_staleness: 60, decimals: 2, guards: feed_guards() }.data(),
+    };
+    lifecycle.send(&[update], &[]).await.unwrap();
+    let config: price_oracle::FeedConfig = lifecycle.account(feed_config).await;
+    assert_eq!(config.cached_price, 0);
+
+    lifecycle.send(&[lifecycle.get_sol_price_ix()], &[]).await.unwrap();
+    let config: price_oracle::FeedConfig = lifecycle.account(feed_config).await;
+    assert_eq!(config.cached_price, 15_000);
+}
+
+#[tokio::test]
+async fn manual_price_is_only_pushed_by_the_oracle_authority() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+
+    // 全域的 OracleUpdater 角色不能替別人的 Oracle 推送人工價格
+    let updater = Keypair::new();
+    lifecycle.send(&[lifecycle.grant_role_ix(access_control::Role::OracleUpdater, updater.pubkey())], &[]).await.unwrap();
+    let result = lifecycle.send(&[lifecycle.push_manual_price_ix(updater.pubkey(), 1)], &[&updater]).await;
+    assert_custom_error(result, price_oracle::PriceOracleError::Unauthorized.into());
+
+    lifecycle.send(&[lifecycle.push_manual_price_ix(lifecycle.authority(), 140)], &[]).await.unwrap();
+    let feed_config: price_oracle::FeedConfig = lifecycle.account(lifecycle.sol_feed_config_pda()).await;
+    assert_eq!(feed_config.manual_price, 140);
+}
+
+#[tokio::test]
+async fn redemption_prices_sol_through_the_aggregated_feed_path() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+    let user = lifecycle.user.insecure_clone();
+    let redemption_manager = Lifecycle::redemption_manager_pda();
+
+    // 人工價格作為備援來源，與主 feed 偏離超過 5% 時標記
+    let fallback = Instruction {
+        program_id: price_oracle::ID,
+        accounts: price_oracle::accounts::SetFallbackSources {
+            oracle_account: lifecycle.oracle.pubkey(),
+            feed_config: lifecycle.sol_feed_config_pda(),
+            authority: lifecycle.authority(),
+        }
+        .to_account_metas(None),
+        data: price_oracle::instruction::SetFallbackSources {
+            fallback_feeds: vec![],
+            manual_max_age: SECONDS_PER_DAY,
+            max_deviation_bps: 500,
+        }
+        .data(),
+    };
+    lifecycle.send(&[fallback], &[]).await.unwrap();
+
+    lifecycle.set_time(lock_start + LOCK_PERIOD_DAYS as i64 * SECONDS_PER_DAY + 3600).await;
+    lifecycle.send(&[lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT)], &[&user]).await.unwrap();
+    lifecycle.set_mock_feed("sol", SOL_PRICE).await;
+
+    // 來源彼此偏離時不以彙整價格出金
+    lifecycle.send(&[lifecycle.push_manual_price_ix(lifecycle.authority(), 200)], &[]).await.unwrap();
+    let result = lifecycle.send(&[lifecycle.execute_redeem_ix(0)], &[&user]).await;
+    assert_custom_error(result, price_oracle::PriceOracleError::PriceDeviationFlagged.into());
+
+    // 來源一致後以彙整價格出金，並寫入 get_price 使用的快取
+    lifecycle.send(&[lifecycle.push_manual_price_ix(lifecycle.authority(), 140)], &[]).await.unwrap();
+    let pool_lamports = lifecycle.lamports(redemption_manager).await;
+    lifecycle.send(&[lifecycle.execute_redeem_ix(0)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.lamports(redemption_manager).await, pool_lamports - 10_714_285_714);
+    let config: price_oracle::FeedConfig = lifecycle.account(lifecycle.sol_feed_config_pda()).await;
+    assert_eq!(config.cached_price, 140);
+    assert_eq!(config.last_update_timestamp, lifecycle.now().await);
+}
+
+#[tokio::test]
+async fn timelock_delay_change_waits_for_the_current_delay() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    let current_delay = state.timelock_delay;
+
+    lifecycle.send(&[lifecycle.queue_timelock_delay_ix(3600)], &[]).await.unwrap();
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.timelock_delay, current_delay);
+
+    // 縮短延遲同樣要等待目前的延遲時間才能生效
+    let kind = asset_manager::QueuedUpdateKind::TimelockDelay;
+    lifecycle.warp(current_delay - 1).await;
+    let result = lifecycle.send(&[lifecycle.execute_queued_update_ix(kind)], &[]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::TimelockNotExpired.into());
+
+    lifecycle.warp(1).await;
+    lifecycle.send(&[lifecycle.execute_queued_update_ix(kind)], &[]).await.unwrap();
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.timelock_delay, 3600);
+    assert!(state.pending_timelock_delay.is_none());
+}
+
+#[tokio::test]
+async fn role_holders_can_pause_and_set_minting_limits() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+    let operator = Keypair::new();
+
+    // 未授予角色前不能暫停系統或調整鑄造上限
+    let result = lifecycle.send(&[Lifecycle::emergency_stop_ix(operator.pubkey())], &[&operator]).await;
+    assert_custom_error(result, access_control::AccessControlError::Unauthorized.into());
+    let mut limits_ix = lifecycle.set_minting_limits_ix(2 * GLOBAL_MINT_LIMIT, USER_MINT_LIMIT, EPOCH_MINT_LIMIT, SECONDS_PER_DAY);
+    limits_ix.accounts.last_mut().unwrap().pubkey = operator.pubkey();
+    let result = lifecycle.send(&[limits_ix.clone()], &[&operator]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::UnauthorizedAccount.into());
+
+    let grants = [
+        lifecycle.grant_role_ix(access_control::Role::Pauser, operator.pubkey()),
+        lifecycle.grant_role_ix(access_control::Role::Minter, operator.pubkey()),
+    ];
+    lifecycle.send(&grants, &[]).await.unwrap();
+
+    lifecycle.send(&[Lifecycle::emergency_stop_ix(operator.pubkey())], &[&operator]).await.unwrap();
+    let access_control: access_control::AccessControl = lifecycle.account(Lifecycle::access_control_pda()).await;
+    assert!(access_control.is_paused);
+
+    lifecycle.send(&[limits_ix], &[&operator]).await.unwrap();
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.minting_limit, 2 * GLOBAL_MINT_LIMIT);
+
+    // Pauser 只能暫停，恢復仍須由管理員執行
+    let result = lifecycle.send(&[Lifecycle::resume_ix(operator.pubkey())], &[&operator]).await;
+    assert_custom_error(result, access_control::AccessControlError::Unauthorized.into());
+    lifecycle.send(&[Lifecycle::resume_ix(lifecycle.authority())], &[]).await.unwrap();
+    let access_control: access_control::AccessControl = lifecycle.account(Lifecycle::access_control_pda()).await;
+    assert!(!access_control.is_paused);
+}
+
+#[tokio::test]
+async fn multisig_takes_over_admin_and_signs_allow_listed_calls() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+    let cosigner = Keypair::new();
+    let multisig = Lifecycle::multisig_pda();
+    let signers = vec![lifecycle.authority(), cosigner.pubkey()];
+    lifecycle.send(&[lifecycle.create_multisig_ix(signers, 2)], &[]).await.unwrap();
+
+    // 建立 multisig 的同時交出兩個程式的管理權，原本的單一金鑰不能再直接呼叫管理指令
+    let access_control: access_control::AccessControl = lifecycle.account(Lifecycle::access_control_pda()).await;
+    assert_eq!(access_control.admin, multisig);
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.authority, multisig);
+    let limits_ix = lifecycle.set_minting_limits_ix(2 * GLOBAL_MINT_LIMIT, USER_MINT_LIMIT, EPOCH_MINT_LIMIT, SECONDS_PER_DAY);
+    let result = lifecycle.send(&[limits_ix], &[]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::UnauthorizedAccount.into());
+
+    let call = access_control::AssetManagerCall::SetMintingLimits {
+        global_limit: 2 * GLOBAL_MINT_LIMIT,
+        user_limit: USER_MINT_LIMIT,
+        epoch_limit: EPOCH_MINT_LIMIT,
+        epoch_duration: SECONDS_PER_DAY,
+    };
+    let proposal = lifecycle.create_proposal_ix(0, access_control::ProposalAction::AssetManager { call });
+    lifecycle.send(&[proposal], &[]).await.unwrap();
+
+    let target_accounts = vec![
+        AccountMeta::new_readonly(asset_manager::ID, false),
+        AccountMeta::new(Lifecycle::state_pda(), false),
+        AccountMeta::new_readonly(Lifecycle::access_control_pda(), false),
+        AccountMeta::new_readonly(multisig, false),
+    ];
+    let result = lifecycle.send(&[lifecycle.execute_ix(0, target_accounts.clone())], &[]).await;
+    assert_custom_error(result, access_control::AccessControlError::ThresholdNotMet.into());
+
+    lifecycle.send(&[Lifecycle::approve_ix(0, cosigner.pubkey())], &[&cosigner]).await.unwrap();
+    lifecycle.send(&[lifecycle.execute_ix(0, target_accounts.clone())], &[]).await.unwrap();
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.minting_limit, 2 * GLOBAL_MINT_LIMIT);
+
+    let result = lifecycle.send(&[lifecycle.execute_ix(0, target_accounts)], &[]).await;
+    assert_custom_error(result, access_control::AccessControlError::ProposalAlreadyExecuted.into());
+}
+
+#[tokio::test]
+async fn multisig_proposals_expire_and_lapse_on_signer_rotation() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+    let cosigner = Keypair::new();
+    let replacement = Keypair::new();
+    let pauser = Pubkey::new_unique();
+    let signers = vec![lifecycle.authority(), cosigner.pubkey()];
+    lifecycle.send(&[lifecycle.create_multisig_ix(signers, 2)], &[]).await.unwrap();
+
+    let grant_pauser = access_control::ProposalAction::GrantRole { role: access_control::Role::Pauser, account: pauser };
+    let rotate = access_control::ProposalAction::SetMultisig {
+        signers: vec![lifecycle.authority(), replacement.pubkey()],
+        threshold: 2,
+    };
+    lifecycle.send(&[lifecycle.create_proposal_ix(0, grant_pauser.clone())], &[]).await.unwrap();
+    lifecycle.send(&[lifecycle.create_proposal_ix(1, rotate)], &[]).await.unwrap();
+    lifecycle.send(&[Lifecycle::approve_ix(1, cosigner.pubkey())], &[&cosigner]).await.unwrap();
+    lifecycle.send(&[lifecycle.execute_ix(1, Vec::new())], &[]).await.unwrap();
+
+    // 換屆前建立的提案不能再由新一屆核准或執行
+    let result = lifecycle.send(&[Lifecycle::approve_ix(0, replacement.pubkey())], &[&replacement]).await;
+    assert_custom_error(result, access_control::AccessControlError::StaleProposal.into());
+    let result = lifecycle.send(&[lifecycle.execute_ix(0, Vec::new())], &[]).await;
+    assert_custom_error(result, access_control::AccessControlError::StaleProposal.into());
+
+    // 逾期的提案即使達到門檻也不能執行
+    lifecycle.send(&[lifecycle.create_proposal_ix(2, grant_pauser.clone())], &[]).await.unwrap();
+    lifecycle.send(&[Lifecycle::approve_ix(2, replacement.pubkey())], &[&replacement]).await.unwrap();
+    lifecycle.warp(access_control::PROPOSAL_LIFETIME).await;
+    let result = lifecycle.send(&[lifecycle.execute_ix(2, Vec::new())], &[]).await;
+    assert_custom_error(result, access_control::AccessControlError::ProposalExpired.into());
+
+    lifecycle.send(&[lifecycle.create_proposal_ix(3, grant_pauser)], &[]).await.unwrap();
+    lifecycle.send(&[Lifecycle::approve_ix(3, replacement.pubkey())], &[&replacement]).await.unwrap();
+    lifecycle.send(&[lifecycle.execute_ix(3, Vec::new())], &[]).await.unwrap();
+    let access_control: access_control::AccessControl = lifecycle.account(Lifecycle::access_control_pda()).await;
+    assert!(access_control.has_role(access_control::Role::Pauser, &pauser));
+}
+
+#[tokio::test]
+async fn xxusd_mint_authority_is_handed_to_the_state_pda() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+    let (mint_authority, _) = lifecycle.mint_authority_pda();
+    let xxusd_mint = lifecycle.xxusd_mint.pubkey();
+
+    let mint: spl_token::state::Mint = lifecycle.unpack(xxusd_mint).await;
+    assert_eq!(mint.mint_authority, COption::Some(mint_authority));
+    let vault: spl_token::state::Account =
+        lifecycle.unpack(get_associated_token_address(&mint_authority, &xxusd_mint)).await;
+    assert_eq!(vault.owner, mint_authority);
+    assert_eq!(vault.mint, xxusd_mint);
+
+    // 鑄幣權已移交，部署者無法再次移交
+    let result = lifecycle.send(&[lifecycle.accept_mint_authority_ix()], &[]).await;
+    assert_custom_error(result, xxusd_token::XXUSDError::Unauthorized.into());
+}
+
+#[tokio::test]
+async fn a_second_state_cannot_take_over_collateral_management() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap().await;
+    let authority = lifecycle.authority();
+    let attacker = Keypair::new();
+    let fund = system_instruction::transfer(&authority, &attacker.pubkey(), solana_sdk::native_token::LAMPORTS_PER_SOL);
+    lifecycle.send(&[fund], &[]).await.unwrap();
+
+    // 狀態帳戶只能建立在唯一的 PDA 上，攻擊者無法另建一個由自己掌管的狀態
+    let fake_state = Keypair::new().pubkey();
+    let result = lifecycle.send(&[lifecycle.initialize_ix(fake_state, attacker.pubkey())], &[&attacker]).await;
+    assert_custom_error(result, anchor_lang::error::ErrorCode::ConstraintSeeds.into());
+    let result = lifecycle.send(&[lifecycle.initialize_ix(Lifecycle::state_pda(), attacker.pubkey())], &[&attacker]).await;
+    assert!(result.is_err());
+
+    // 既有的 jupSOL 設定不能被修改
+    let update = Instruction {
+        program_id: asset_manager::ID,
+        accounts: asset_manager::accounts::UpdateCollateral {
+            state: Lifecycle::state_pda(),
+            collateral: lifecycle.collateral_pda(),
+            authority: attacker.pubkey(),
+        }
+        .to_account_metas(None),
+        data: asset_manager::instruction::UpdateCollateral { ltv_bps: 9500, deposit_cap: u64::MAX, enabled: true }.data(),
+    };
+    let result = lifecycle.send(&[update], &[&attacker]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::UnauthorizedAccount.into());
+
+    // Oracle 已註冊的新 LST 也不能被搶先登記為抵押品
+    let new_lst = Keypair::new();
+    lifecycle.create_mint(&new_lst, JUPSOL_DECIMALS).await;
+    let lst_asset = Pubkey::find_program_address(
+        &[b"lst", lifecycle.oracle.pubkey().as_ref(), new_lst.pubkey().as_ref()],
+        &price_oracle::ID,
+    )
+    .0;
+    let register = Instruction {
+        program_id: price_oracle::ID,
+        accounts: price_oracle::accounts::AddLstAsset {
+            oracle_account: lifecycle.oracle.pubkey(),
+            lst_asset,
+            price_history: Lifecycle::price_history_pda(lst_asset),
+            mint: new_lst.pubkey(),
+            price_feed: lifecycle.mock_feed_pda("jupsol"),
+            apy_feed: lifecycle.mock_feed_pda("jupsol_apy"),
+            authority,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: price_oracle::instruction::AddLstAsset {
+            symbol: "NEWLST".to_string(),
+            max_staleness: 60,
+            guards: feed_guards(),
+        }
+        .data(),
+    };
+    lifecycle.send(&[register], &[]).await.unwrap();
+    let result = lifecycle
+        .send(&[lifecycle.add_collateral_ix(&new_lst.pubkey(), attacker.pubkey())], &[&attacker])
+        .await;
+    assert_custom_error(result, asset_manager::AssetManagerError::UnauthorizedAccount.into());
+    lifecycle.send(&[lifecycle.add_collateral_ix(&new_lst.pubkey(), authority)], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn deposit_respects_collateral_cap_and_enabled_flag() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap_with_prices().await;
+    let user = lifecycle.user.insecure_clone();
+
+    let result = lifecycle.send(&[lifecycle.deposit_ix(DEPOSIT_CAP + 1)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::DepositCapExceeded.into());
+
+    lifecycle.send(&[lifecycle.update_collateral_ix(JUPSOL_LTV_BPS, DEPOSIT_CAP, false)], &[]).await.unwrap();
+    let result = lifecycle.send(&[lifecycle.deposit_ix(DEPOSIT_AMOUNT)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::CollateralDisabled.into());
+}
+
+#[tokio::test]
+async fn mint_is_bounded_by_recorded_deposit_value() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.deposit_and_mint().await;
+    let user = lifecycle.user.insecure_clone();
+
+    // 存款價值已全數鑄造，再次鑄造沒有可用的抵押額度
+    let result = lifecycle.send(&[lifecycle.mint_ix(1)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::InsufficientDepositValue.into());
+
+    // 追加存款後只鑄造新增的部分，並開立第二個鎖倉部位
+    lifecycle.deposit_more(DEPOSIT_AMOUNT / 2).await;
+    lifecycle.send(&[lifecycle.mint_ix(1)], &[&user]).await.unwrap();
+
+    let user_deposit: asset_manager::UserDeposit = lifecycle.account(lifecycle.user_deposit_pda()).await;
+    assert_eq!(user_deposit.amount, DEPOSIT_VALUE + DEPOSIT_VALUE / 2);
+    assert_eq!(user_deposit.xxusd_amount, user_deposit.amount);
+    let user_xxusd = lifecycle.user_ata(&lifecycle.xxusd_mint.pubkey());
+    assert_eq!(
+        lifecycle.token_balance(user_xxusd).await,
+        DEPOSIT_VALUE + DEPOSIT_VALUE / 2 - 2 * LOCKED_BY_PRODUCT
+    );
+
+    let lock_index: lock_manager::UserLockIndex = lifecycle.account(lifecycle.lock_index_pda()).await;
+    assert_eq!(lock_index.next_id, 2);
+    assert_eq!(lock_index.open_ids, vec![0, 1]);
+    for lock_id in 0..2 {
+        let lock_record: lock_manager::LockRecord =
+            lifecycle.account(lifecycle.lock_record_pda(lock_id)).await;
+        assert_eq!(lock_record.id, lock_id);
+        assert_eq!(lock_record.amount, LOCKED_BY_PRODUCT);
+    }
+}
+
+#[tokio::test]
+async fn fully_released_position_frees_its_lock_index_slot() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap_with_prices().await;
+    let user = lifecycle.user.insecure_clone();
+    let max_open = lock_manager::MAX_OPEN_LOCKS as u64;
+
+    // 放寬抵押品與鑄造上限，讓用戶可以開滿所有鎖倉部位
+    let ix = lifecycle.update_collateral_ix(JUPSOL_LTV_BPS, (max_open + 1) * POSITION_DEPOSIT, true);
+    lifecycle.send(&[ix], &[]).await.unwrap();
+    let ix = lifecycle.set_minting_limits_ix(GLOBAL_MINT_LIMIT, GLOBAL_MINT_LIMIT, GLOBAL_MINT_LIMIT, SECONDS_PER_DAY);
+    lifecycle.send(&[ix], &[]).await.unwrap();
+
+    for lock_id in 0..max_open {
+        lifecycle.deposit_more(POSITION_DEPOSIT).await;
+        lifecycle.send(&[lifecycle.mint_ix(lock_id)], &[&user]).await.unwrap();
+    }
+    lifecycle.deposit_more(POSITION_DEPOSIT).await;
+    let result = lifecycle.send(&[lifecycle.mint_ix(max_open)], &[&user]).await;
+    assert_custom_error(result, lock_manager::LockManagerError::TooManyOpenLocks.into());
+
+    // 第一個部位在鎖定期結束後全數釋放，並自索引中移除
+    let lock_record: lock_manager::LockRecord = lifecycle.account(lifecycle.lock_record_pda(0)).await;
+    lifecycle.set_time(lock_record.start_time + lock_record.lock_period as i64 * SECONDS_PER_DAY).await;
+    lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await.unwrap();
+    let lock_index: lock_manager::UserLockIndex = lifecycle.account(lifecycle.lock_index_pda()).await;
+    assert_eq!(lock_index.open_ids, (1..max_open).collect::<Vec<_>>());
+
+    // 空出的位置可再開立新部位，編號繼續遞增
+    lifecycle.send(&[lifecycle.mint_ix(max_open)], &[&user]).await.unwrap();
+    let lock_index: lock_manager::UserLockIndex = lifecycle.account(lifecycle.lock_index_pda()).await;
+    assert_eq!(lock_index.next_id, max_open + 1);
+    assert_eq!(lock_index.open_ids, (1..=max_open).collect::<Vec<_>>());
+    let lock_record: lock_manager::LockRecord =
+        lifecycle.account(lifecycle.lock_record_pda(max_open)).await;
+    assert_eq!(lock_record.amount, LOCKED_BY_PRODUCT);
+}
+
+#[tokio::test]
+async fn mint_enforces_user_global_and_epoch_limits() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap_with_prices().await;
+    let user = lifecycle.user.insecure_clone();
+
+    // 上限設定必須一致：單一用戶與週期額度不可超過總上限
+    let ix = lifecycle.set_minting_limits_ix(DEPOSIT_VALUE, 2 * DEPOSIT_VALUE, DEPOSIT_VALUE, SECONDS_PER_DAY);
+    let result = lifecycle.send(&[ix], &[]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::InvalidMintingLimits.into());
+
+    lifecycle.send(&[lifecycle.deposit_ix(DEPOSIT_AMOUNT)], &[&user]).await.unwrap();
+
+    // 單一用戶上限
+    let ix = lifecycle.set_minting_limits_ix(GLOBAL_MINT_LIMIT, DEPOSIT_VALUE - 1, EPOCH_MINT_LIMIT, SECONDS_PER_DAY);
+    lifecycle.send(&[ix], &[]).await.unwrap();
+    let result = lifecycle.send(&[lifecycle.mint_ix(0)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::UserMintingLimitExceeded.into());
+
+    // 總流通量上限
+    let ix = lifecycle.set_minting_limits_ix(DEPOSIT_VALUE - 1, DEPOSIT_VALUE - 1, DEPOSIT_VALUE - 1, SECONDS_PER_DAY);
+    lifecycle.send(&[ix], &[]).await.unwrap();
+    let result = lifecycle.send(&[lifecycle.mint_ix(0)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::MintingLimitExceeded.into());
+
+    // 週期預算用完後，需等下一個週期才能再鑄造
+    let ix = lifecycle.set_minting_limits_ix(GLOBAL_MINT_LIMIT, USER_MINT_LIMIT, DEPOSIT_VALUE, SECONDS_PER_DAY);
+    lifecycle.send(&[ix], &[]).await.unwrap();
+    lifecycle.send(&[lifecycle.mint_ix(0)], &[&user]).await.unwrap();
+    lifecycle.deposit_more(DEPOSIT_AMOUNT / 2).await;
+    let result = lifecycle.send(&[lifecycle.mint_ix(1)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::EpochMintingLimitExceeded.into());
+
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.outstanding_supply, DEPOSIT_VALUE);
+    assert_eq!(state.epoch_minted, DEPOSIT_VALUE);
+    let user_mint: asset_manager::UserMintRecord = lifecycle.account(lifecycle.user_mint_pda()).await;
+    assert_eq!(user_mint.outstanding, DEPOSIT_VALUE);
+
+    lifecycle.warp(SECONDS_PER_DAY).await;
+    lifecycle.refresh_jupsol().await;
+    lifecycle.send(&[lifecycle.mint_ix(1)], &[&user]).await.unwrap();
+
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.outstanding_supply, DEPOSIT_VALUE + DEPOSIT_VALUE / 2);
+    assert_eq!(state.epoch_minted, DEPOSIT_VALUE / 2);
+    let user_mint: asset_manager::UserMintRecord = lifecycle.account(lifecycle.user_mint_pda()).await;
+    assert_eq!(user_mint.outstanding, DEPOSIT_VALUE + DEPOSIT_VALUE / 2);
+}
+
+#[tokio::test]
+async fn mint_rejects_disabled_collateral_and_foreign_xxusd_accounts() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.bootstrap_with_prices().await;
+    let user = lifecycle.user.insecure_clone();
+    lifecycle.send(&[lifecycle.deposit_ix(DEPOSIT_AMOUNT)], &[&user]).await.unwrap();
+
+    // 鑄造的 xxUSD 只能發給用戶自己的 xxUSD 帳戶
+    let mut instruction = lifecycle.mint_ix(0);
+    instruction.accounts[3].pubkey = get_associated_token_address(
+        &Lifecycle::lock_manager_pda(),
+        &lifecycle.xxusd_mint.pubkey(),
+    );
+    let result = lifecycle.send(&[instruction], &[&user]).await;
+    assert_custom_error(result, anchor_lang::error::ErrorCode::ConstraintTokenOwner.into());
+    let mut instruction = lifecycle.mint_ix(0);
+    instruction.accounts[3].pubkey = lifecycle.user_ata(&lifecycle.jupsol_mint.pubkey());
+    let result = lifecycle.send(&[instruction], &[&user]).await;
+    assert_custom_error(result, anchor_lang::error::ErrorCode::ConstraintTokenMint.into());
+
+    // 停用的抵押品不能再用來鑄造
+    lifecycle.send(&[lifecycle.update_collateral_ix(JUPSOL_LTV_BPS, DEPOSIT_CAP, false)], &[]).await.unwrap();
+    let result = lifecycle.send(&[lifecycle.mint_ix(0)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::CollateralDisabled.into());
+}
+
+#[tokio::test]
+async fn full_withdrawal_waits_for_the_locked_xxusd_to_be_released() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+    let user = lifecycle.user.insecure_clone();
+    let user_jupsol = lifecycle.user_ata(&lifecycle.jupsol_mint.pubkey());
+    let user_xxusd = lifecycle.user_ata(&lifecycle.xxusd_mint.pubkey());
+
+    // 鑄造時鎖倉的部分仍計入應償還的 xxUSD，領回前餘額不足以全數償還
+    let result = lifecycle.send(&[lifecycle.withdraw_ix(DEPOSIT_VALUE)], &[&user]).await;
+    assert_custom_error(result, spl_token::error::TokenError::InsufficientFunds as u32);
+
+    // 鎖定期結束後領回全部鎖倉餘額，即可償還全部 xxUSD 並取回全部抵押品
+    lifecycle.set_time(lock_start + LOCK_PERIOD_DAYS as i64 * SECONDS_PER_DAY).await;
+    lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.token_balance(user_xxusd).await, DEPOSIT_VALUE);
+    lifecycle.send(&[lifecycle.withdraw_ix(DEPOSIT_VALUE)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.token_balance(user_xxusd).await, 0);
+    assert_eq!(lifecycle.token_balance(user_jupsol).await, DEPOSIT_AMOUNT);
+    let user_deposit: asset_manager::UserDeposit = lifecycle.account(lifecycle.user_deposit_pda()).await;
+    assert_eq!(user_deposit.collateral_amount, 0);
+    assert_eq!(user_deposit.xxusd_amount, 0);
+}
+
+#[tokio::test]
+async fn withdraw_returns_collateral_proportional_to_repaid_xxusd() {
+    let mut lifecycle = Lifecycle::new().await;
+    lifecycle.deposit_and_mint().await;
+    let user = lifecycle.user.insecure_clone();
+    let user_jupsol = lifecycle.user_ata(&lifecycle.jupsol_mint.pubkey());
+    let user_xxusd = lifecycle.user_ata(&lifecycle.xxusd_mint.pubkey());
+    let distributed = DEPOSIT_VALUE - LOCKED_BY_PRODUCT;
+
+    let result = lifecycle.send(&[lifecycle.withdraw_ix(DEPOSIT_VALUE + 1)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::InvalidAmount.into());
+
+    // 償還四分之一的 xxUSD，取回四分之一的抵押品
+    let repay = DEPOSIT_VALUE / 4;
+    lifecycle.send(&[lifecycle.withdraw_ix(repay)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.token_balance(user_jupsol).await, DEPOSIT_AMOUNT / 4);
+    assert_eq!(lifecycle.token_balance(lifecycle.vault_pda()).await, DEPOSIT_AMOUNT - DEPOSIT_AMOUNT / 4);
+    assert_eq!(lifecycle.token_balance(user_xxusd).await, distributed - repay);
+    let user_deposit: asset_manager::UserDeposit = lifecycle.account(lifecycle.user_deposit_pda()).await;
+    assert_eq!(user_deposit.collateral_amount, DEPOSIT_AMOUNT - DEPOSIT_AMOUNT / 4);
+    assert_eq!(user_deposit.amount, DEPOSIT_VALUE - DEPOSIT_VALUE / 4);
+    assert_eq!(user_deposit.xxusd_amount, DEPOSIT_VALUE - repay);
+    let collateral: asset_manager::CollateralConfig = lifecycle.account(lifecycle.collateral_pda()).await;
+    assert_eq!(collateral.total_deposited, DEPOSIT_AMOUNT - DEPOSIT_AMOUNT / 4);
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.outstanding_supply, DEPOSIT_VALUE - repay);
+    let user_mint: asset_manager::UserMintRecord = lifecycle.account(lifecycle.user_mint_pda()).await;
+    assert_eq!(user_mint.outstanding, DEPOSIT_VALUE - repay);
+
+    // 調低 LTV 後剩餘抵押品不足以支撐部分取回
+    lifecycle.send(&[lifecycle.update_collateral_ix(100, DEPOSIT_CAP, true)], &[]).await.unwrap();
+    let result = lifecycle.send(&[lifecycle.withdraw_ix(repay)], &[&user]).await;
+    assert_custom_error(result, asset_manager::AssetManagerError::InsufficientCollateral.into());
+}
+
+#[tokio::test]
+async fn release_daily_rejects_second_release_on_same_day() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+    let user = lifecycle.user.insecure_clone();
+
+    // 鎖定當天的起始時間即視為最後釋放時間
+    let result = lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await;
+    assert_custom_error(result, lock_manager::LockManagerError::AlreadyReleasedToday.into());
+
+    lifecycle.set_time(lock_start + SECONDS_PER_DAY).await;
+    lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await.unwrap();
+
+    lifecycle.warp(3600).await;
+    let result = lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await;
+    assert_custom_error(result, lock_manager::LockManagerError::AlreadyReleasedToday.into());
+}
+
+#[tokio::test]
+async fn a_lock_only_releases_and_redeems_the_mint_it_was_opened_with() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+    let user = lifecycle.user.insecure_clone();
+    let xxusd_mint = lifecycle.xxusd_mint.pubkey();
+    let lock_vault = get_associated_token_address(&Lifecycle::lock_manager_pda(), &xxusd_mint);
+
+    // 用戶以自建的無價值代幣開立鎖倉部位
+    let fake_mint = Keypair::new();
+    lifecycle.create_mint(&fake_mint, 6).await;
+    let fake_mint = fake_mint.pubkey();
+    let setup = [
+        lifecycle.create_ata_ix(&user.pubkey(), &fake_mint),
+        lifecycle.create_ata_ix(&Lifecycle::lock_manager_pda(), &fake_mint),
+        spl_token::instruction::mint_to(
+            &spl_token::ID,
+            &fake_mint,
+            &lifecycle.user_ata(&fake_mint),
+            &lifecycle.authority(),
+            &[],
+            LOCKED_BY_PRODUCT,
+        )
+        .unwrap(),
+    ];
+    lifecycle.send(&setup, &[]).await.unwrap();
+    let ix = lifecycle.lock_ix(&fake_mint, 1, LOCKED_BY_PRODUCT, DAILY_RELEASE);
+    lifecycle.send(&[ix], &[&user]).await.unwrap();
+    let lock_record: lock_manager::LockRecord = lifecycle.account(lifecycle.lock_record_pda(1)).await;
+    assert_eq!(lock_record.mint, fake_mint);
+
+    // 該部位不能從 xxUSD 金庫釋放，只能領回原本鎖入的代幣
+    lifecycle.set_time(lock_start + SECONDS_PER_DAY).await;
+    let result = lifecycle.send(&[lifecycle.release_ix(1)], &[&user]).await;
+    assert_custom_error(result, lock_manager::LockManagerError::InvalidMint.into());
+    assert_eq!(lifecycle.token_balance(lock_vault).await, LOCKED_BY_PRODUCT);
+    lifecycle.send(&[lifecycle.release_mint_ix(1, &fake_mint)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.token_balance(lifecycle.user_ata(&fake_mint)).await, DAILY_RELEASE);
+
+    // 也不能以該部位的額度贖回 xxUSD
+    lifecycle.set_time(lock_start + LOCK_PERIOD_DAYS as i64 * SECONDS_PER_DAY + 3600).await;
+    let result = lifecycle.send(&[lifecycle.initiate_redeem_ix(1, REDEEM_AMOUNT)], &[&user]).await;
+    assert_custom_error(result, redemption_manager::RedemptionError::InvalidMint.into());
+}
+
+#[tokio::test]
+async fn redemption_without_a_mint_record_still_releases_outstanding_supply() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+    let minter = lifecycle.user.insecure_clone();
+    let minter_mint = lifecycle.user_mint_pda();
+    let xxusd_mint = lifecycle.xxusd_mint.pubkey();
+
+    // 第二位用戶從鑄造者手上取得 xxUSD，鎖倉一部分並保留足夠贖回的餘額，沒有 asset_manager 的鑄造紀錄
+    let holder = Keypair::new();
+    let setup = [
+        system_instruction::transfer(&lifecycle.authority(), &holder.pubkey(), solana_sdk::native_token::LAMPORTS_PER_SOL),
+        lifecycle.create_ata_ix(&holder.pubkey(), &xxusd_mint),
+        spl_token::instruction::transfer(
+            &spl_token::ID,
+            &lifecycle.user_ata(&xxusd_mint),
+            &get_associated_token_address(&holder.pubkey(), &xxusd_mint),
+            &minter.pubkey(),
+            &[],
+            LOCKED_BY_PRODUCT + REDEEM_AMOUNT,
+        )
+        .unwrap(),
+    ];
+    lifecycle.send(&setup, &[&minter]).await.unwrap();
+
+    lifecycle.user = holder.insecure_clone();
+    let holder_mint = lifecycle.user_mint_pda();
+    lifecycle.send(&[lifecycle.lock_ix(&xxusd_mint, 0, LOCKED_BY_PRODUCT, DAILY_RELEASE)], &[&holder]).await.unwrap();
+
+    lifecycle.set_time(lock_start + LOCK_PERIOD_DAYS as i64 * SECONDS_PER_DAY + 3600).await;
+    lifecycle.send(&[lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT)], &[&holder]).await.unwrap();
+    lifecycle.set_mock_feed("sol", SOL_PRICE).await;
+    lifecycle.send(&[lifecycle.execute_redeem_ix(0)], &[&holder]).await.unwrap();
+
+    // 銷毀仍從流通量扣除，鑄造者的紀錄不受影響，也不會替持有者建立紀錄
+    let state: asset_manager::ProgramState = lifecycle.account(Lifecycle::state_pda()).await;
+    assert_eq!(state.outstanding_supply, DEPOSIT_VALUE - REDEEM_AMOUNT);
+    let user_mint: asset_manager::UserMintRecord = lifecycle.account(minter_mint).await;
+    assert_eq!(user_mint.outstanding, DEPOSIT_VALUE);
+    assert!(lifecycle.context.banks_client.get_account(holder_mint).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn initiate_redeem_rejects_before_lock_period_ends() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+    let user = lifecycle.user.insecure_clone();
+
+    lifecycle.set_time(lock_start + (LOCK_PERIOD_DAYS as i64 - 1) * SECONDS_PER_DAY).await;
+    let result = lifecycle.send(&[lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT)], &[&user]).await;
+    assert_custom_error(result, redemption_manager::RedemptionError::LockPeriodNotEnded.into());
+
+    // 超過鎖定期後 14 天的贖回窗口同樣不能發起贖回
+    lifecycle.set_time(lock_start + (LOCK_PERIOD_DAYS as i64 + 15) * SECONDS_PER_DAY).await;
+    let result = lifecycle.send(&[lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT)], &[&user]).await;
+    assert_custom_error(result, redemption_manager::RedemptionError::RedemptionPeriodEnded.into());
+}
+
+#[tokio::test]
+async fn initiate_redeem_only_pays_into_the_redemption_vault() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+    let user = lifecycle.user.insecure_clone();
+    lifecycle.set_time(lock_start + LOCK_PERIOD_DAYS as i64 * SECONDS_PER_DAY + 3600).await;
+
+    // 把自己的 xxUSD 帳戶當成贖回金庫會被拒絕
+    let mut instruction = lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT);
+    instruction.accounts[2].pubkey = lifecycle.user_ata(&lifecycle.xxusd_mint.pubkey());
+    let result = lifecycle.send(&[instruction], &[&user]).await;
+    assert_custom_error(result, anchor_lang::error::ErrorCode::ConstraintTokenOwner.into());
+
+    // 以其他代幣冒充 xxUSD 同樣會被拒絕
+    let mut instruction = lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT);
+    instruction.accounts[4].pubkey = lifecycle.jupsol_mint.pubkey();
+    let result = lifecycle.send(&[instruction], &[&user]).await;
+    assert_custom_error(result, anchor_lang::error::ErrorCode::ConstraintTokenMint.into());
+}
+
+#[tokio::test]
+async fn finished_lock_and_processed_redemption_are_closed_for_rent() {
+    let mut lifecycle = Lifecycle::new().await;
+    let lock_start = lifecycle.deposit_mint_and_lock().await;
+    let user = lifecycle.user.insecure_clone();
+    let lock_record_address = lifecycle.lock_record_pda(0);
+    let request_address = lifecycle.redemption_request_pda(0);
+
+    // 仍有餘額的鎖倉部位不能關閉
+    let result = lifecycle.send(&[lifecycle.close_lock_ix(0)], &[&user]).await;
+    assert_custom_error(result, lock_manager::LockManagerError::LockNotFinished.into());
+
+    // 贖回請求處理完成前不能關閉
+    lifecycle.set_time(lock_start + LOCK_PERIOD_DAYS as i64 * SECONDS_PER_DAY + 3600).await;
+    lifecycle.send(&[lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT)], &[&user]).await.unwrap();
+    let result = lifecycle.send(&[lifecycle.close_redemption_ix(0)], &[&user]).await;
+    assert_custom_error(result, redemption_manager::RedemptionError::RedemptionNotProcessed.into());
+
+    lifecycle.set_mock_feed("sol", SOL_PRICE).await;
+    lifecycle.send(&[lifecycle.execute_redeem_ix(0)], &[&user]).await.unwrap();
+    let user_lamports = lifecycle.lamports(user.pubkey()).await;
+    let request_rent = lifecycle.lamports(request_address).await;
+    lifecycle.send(&[lifecycle.close_redemption_ix(0)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.lamports(user.pubkey()).await, user_lamports + request_rent);
+    assert!(lifecycle.context.banks_client.get_account(request_address).await.unwrap().is_none());
+
+    // 關閉後同一部位只能在剩餘的鎖定額度內再次發起贖回
+    let result = lifecycle.send(&[lifecycle.initiate_redeem_ix(0, REDEEM_AMOUNT)], &[&user]).await;
+    assert_custom_error(result, redemption_manager::RedemptionError::ExceedsLockedAmount.into());
+    let remaining = LOCKED_BY_PRODUCT - REDEEM_AMOUNT;
+    lifecycle.send(&[lifecycle.initiate_redeem_ix(0, remaining)], &[&user]).await.unwrap();
+    let request: redemption_manager::RedemptionRequest = lifecycle.account(request_address).await;
+    assert!(!request.is_processed);
+    assert_eq!(request.amount, remaining);
+    let lock_redemption: redemption_manager::LockRedemption =
+        lifecycle.account(lifecycle.lock_redemption_pda(0)).await;
+    assert_eq!(lock_redemption.redeemed, LOCKED_BY_PRODUCT);
+
+    // 鎖定期結束後的最後一次釋放領回全部餘額，部位隨即可以關閉
+    lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await.unwrap();
+    let lock_record: lock_manager::LockRecord = lifecycle.account(lock_record_address).await;
+    assert_eq!(lock_record.amount, 0);
+    let result = lifecycle.send(&[lifecycle.release_ix(0)], &[&user]).await;
+    assert_custom_error(result, lock_manager::LockManagerError::NoAmountToRelease.into());
+
+    let user_lamports = lifecycle.lamports(user.pubkey()).await;
+    let lock_rent = lifecycle.lamports(lock_record_address).await;
+    lifecycle.send(&[lifecycle.close_lock_ix(0)], &[&user]).await.unwrap();
+    assert_eq!(lifecycle.lamports(user.pubkey()).await, user_lamports + lock_rent);
+    assert!(lifecycle.context.banks_client.get_account(lock_record_address).await.unwrap().is_none());
+    let lock_index: lock_manager::UserLockIndex = lifecycle.account(lifecycle.lock_index_pda()).await;
+    assert!(lock_index.open_ids.is_empty());
+    assert_eq!(lock_index.next_id, 1);
+}
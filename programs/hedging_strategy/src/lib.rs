@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount};
+use access_control::AccessControl;
 
 declare_id!("2advfuGc93qzj3kxMjLomTrPqTUQb3sitRnPGwr8fDQv");
 
@@ -9,22 +10,47 @@ pub mod hedging_strategy {
 
     pub fn initialize_system_state(ctx: Context<InitializeSystemState>) -> Result<()> {
         ctx.accounts.system_state.is_paused = false;
+        ctx.accounts.system_state.authority = ctx.accounts.authority.key();
         Ok(())
     }
 
     pub fn pause_system(ctx: Context<PauseSystem>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.system_state.authority,
+            HedgingError::Unauthorized
+        );
         ctx.accounts.system_state.is_paused = true;
         Ok(())
     }
 
     pub fn unpause_system(ctx: Context<PauseSystem>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.system_state.authority,
+            HedgingError::Unauthorized
+        );
         ctx.accounts.system_state.is_paused = false;
         Ok(())
     }
 
+    pub fn transfer_authority(ctx: Context<TransferAuthority>, new_authority: Pubkey) -> Result<()> {
+        require!(new_authority != Pubkey::default(), HedgingError::InvalidAuthority);
+
+        let system_state = &mut ctx.accounts.system_state;
+        let old_authority = system_state.authority;
+        system_state.authority = new_authority;
+
+        emit!(AuthorityTransferredEvent {
+            old_authority,
+            new_authority,
+        });
+
+        Ok(())
+    }
+
     pub fn manage_hedging(ctx: Context<ManageHedging>, amount: u64) -> Result<()> {
         // 檢查系統是否處於暫停狀態
         require!(!ctx.accounts.system_state.is_paused, HedgingError::SystemPaused);
+        require!(!ctx.accounts.access_control.is_paused, HedgingError::SystemPaused);
 
         // 檢查金額是否有效
         require!(amount > 0, HedgingError::InvalidAmount);
@@ -83,7 +109,13 @@ pub mod hedging_strategy {
 
 #[derive(Accounts)]
 pub struct InitializeSystemState<'info> {
-    #[account(init, payer = authority, space = 8 + 1)]
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32,
+        seeds = [b"system_state"],
+        bump
+    )]
     pub system_state: Account<'info, SystemState>,
     #[account(mut)]
     pub authority: Signer<'info>,
@@ -92,7 +124,19 @@ pub struct InitializeSystemState<'info> {
 
 #[derive(Accounts)]
 pub struct PauseSystem<'info> {
-    #[account(mut)]
+    #[account(mut, seeds = [b"system_state"], bump)]
+    pub system_state: Account<'info, SystemState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"system_state"],
+        bump,
+        constraint = system_state.authority == authority.key() @ HedgingError::Unauthorized,
+    )]
     pub system_state: Account<'info, SystemState>,
     pub authority: Signer<'info>,
 }
@@ -116,7 +160,14 @@ pub struct ManageHedging<'info> {
         bump
     )]
     pub hedging_record: Account<'info, HedgingRecord>,
+    #[account(seeds = [b"system_state"], bump)]
     pub system_state: Account<'info, SystemState>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
@@ -132,6 +183,7 @@ pub struct HedgingRecord {
 #[account]
 pub struct SystemState {
     pub is_paused: bool,
+    pub authority: Pubkey,
 }
 
 #[event]
@@ -141,6 +193,12 @@ pub struct HedgingCompletedEvent {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
 #[error_code]
 pub enum HedgingError {
     #[msg("System is paused")]
@@ -153,4 +211,8 @@ pub enum HedgingError {
     InvalidOwner,
     #[msg("Hedging operation is already in progress")]
     HedgingInProgress,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid authority")]
+    InvalidAuthority,
 }
\ No newline at end of file
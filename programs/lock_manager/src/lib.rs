@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 use anchor_spl::associated_token::AssociatedToken;
+use access_control::AccessControl;
 
 declare_id!("63BVdYQjLtQCSYg58iRhqdWNrzQ2aSs7z7vZ3tgyAj1E");
 
@@ -14,27 +15,25 @@ pub mod lock_manager {
         lock_period: u64,
         daily_release: u64,
     ) -> Result<()> {
+        require!(!ctx.accounts.access_control.is_paused, LockManagerError::SystemPaused);
+
         // 驗證輸入參數
-        require!(amount > 0, LockManagerError::InvalidAmount);
-        require!(lock_period > 0, LockManagerError::InvalidLockPeriod);
-        require!(daily_release > 0, LockManagerError::InvalidDailyRelease);
-        require!(
-            amount >= daily_release * lock_period,
-            LockManagerError::InvalidLockParameters
-        );
+        validate_lock_params(amount, lock_period, daily_release)?;
 
         // 檢查用戶餘額
         let user_balance = ctx.accounts.user_token_account.amount;
         require!(user_balance >= amount, LockManagerError::InsufficientBalance);
 
-        // 創建鎖定記錄
-        let lock_record = &mut ctx.accounts.lock_record;
-        lock_record.owner = ctx.accounts.user.key();
-        lock_record.amount = amount;
-        lock_record.lock_period = lock_period;
-        lock_record.daily_release = daily_release;
-        lock_record.start_time = Clock::get()?.unix_timestamp;
-        lock_record.last_release_time = lock_record.start_time;
+        // 在用戶索引中開立新部位並創建鎖定記錄
+        let lock_id = ctx.accounts.lock_index.open_position(ctx.accounts.user.key())?;
+        ctx.accounts.lock_record.open(
+            ctx.accounts.user.key(),
+            ctx.accounts.xxusd_mint.key(),
+            lock_id,
+            amount,
+            lock_period,
+            daily_release,
+        )?;
 
         // 轉移 xxUSD 到鎖定合約地址
         let cpi_accounts = Transfer {
@@ -49,6 +48,7 @@ pub mod lock_manager {
         // 發出鎖定事件
         emit!(LockEvent {
             user: ctx.accounts.user.key(),
+            lock_id,
             amount,
             lock_period,
             daily_release,
@@ -57,36 +57,80 @@ pub mod lock_manager {
         Ok(())
     }
 
-    pub fn release_daily_xxusd(ctx: Context<ReleaseDailyXxUSD>) -> Result<()> {
+    /// 由 asset_manager 在鑄造時呼叫：將其金庫中屬於商品價格的 xxUSD 鎖定給用戶
+    pub fn lock_minted_xxusd(
+        ctx: Context<LockMintedXxUSD>,
+        amount: u64,
+        lock_period: u64,
+        daily_release: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.access_control.is_paused, LockManagerError::SystemPaused);
+        validate_lock_params(amount, lock_period, daily_release)?;
+        require!(ctx.accounts.source.amount >= amount, LockManagerError::InsufficientBalance);
+
+        let lock_id = ctx.accounts.lock_index.open_position(ctx.accounts.user.key())?;
+        ctx.accounts.lock_record.open(
+            ctx.accounts.user.key(),
+            ctx.accounts.xxusd_mint.key(),
+            lock_id,
+            amount,
+            lock_period,
+            daily_release,
+        )?;
+
+        // asset_manager 的 PDA 已在 CPI 中簽名
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.source.to_account_info(),
+            to: ctx.accounts.lock_vault.to_account_info(),
+            authority: ctx.accounts.source_authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(LockEvent {
+            user: ctx.accounts.user.key(),
+            lock_id,
+            amount,
+            lock_period,
+            daily_release,
+        });
+
+        Ok(())
+    }
+
+    pub fn release_daily_xxusd(ctx: Context<ReleaseDailyXxUSD>, lock_id: u64) -> Result<()> {
+        require!(!ctx.accounts.access_control.is_paused, LockManagerError::SystemPaused);
+
         let lock_record = &mut ctx.accounts.lock_record;
         let current_time = Clock::get()?.unix_timestamp;
 
         // 驗證所有者
         require!(lock_record.owner == ctx.accounts.user.key(), LockManagerError::InvalidOwner);
 
-        // 檢查鎖定期是否已結束
-        require!(
-            current_time < lock_record.start_time + (lock_record.lock_period as i64 * 86400),
-            LockManagerError::LockPeriodEnded
-        );
-
-        // 確保每日只能釋放一次
-        let last_release_date = lock_record.last_release_time / 86400;
-        let current_date = current_time / 86400;
-        require!(last_release_date < current_date, LockManagerError::AlreadyReleasedToday);
-
-        // 計算可釋放的金額
-        let days_since_last_release = (current_time - lock_record.last_release_time) / 86400;
-        let releasable_amount = lock_record.daily_release.saturating_mul(days_since_last_release as u64);
-        let remaining_locked_amount = lock_record.amount;
-
-        let release_amount = releasable_amount.min(remaining_locked_amount);
+        // 鎖定期結束後一次釋放全部剩餘餘額，包含每日額度整除後留下的零頭
+        let lock_end_time = lock_record.start_time + (lock_record.lock_period as i64 * 86400);
+        let release_amount = if current_time >= lock_end_time {
+            lock_record.amount
+        } else {
+            // 確保每日只能釋放一次
+            let last_release_date = lock_record.last_release_time / 86400;
+            let current_date = current_time / 86400;
+            require!(last_release_date < current_date, LockManagerError::AlreadyReleasedToday);
+
+            // 計算可釋放的金額
+            let days_since_last_release = (current_time - lock_record.last_release_time) / 86400;
+            let releasable_amount = lock_record.daily_release.saturating_mul(days_since_last_release as u64);
+            releasable_amount.min(lock_record.amount)
+        };
 
         require!(release_amount > 0, LockManagerError::NoAmountToRelease);
 
-        // 更新鎖定記錄
+        // 更新鎖定記錄，全數釋放後自索引中移除該部位
         lock_record.amount = lock_record.amount.saturating_sub(release_amount);
         lock_record.last_release_time = current_time;
+        if lock_record.amount == 0 {
+            ctx.accounts.lock_index.close_position(lock_id);
+        }
 
         // 轉移釋放的 xxUSD 到用戶帳戶
         let seeds = &[
@@ -107,13 +151,26 @@ pub mod lock_manager {
         // 發出釋放事件
         emit!(ReleaseEvent {
             user: ctx.accounts.user.key(),
+            lock_id,
             amount: release_amount,
         });
 
         Ok(())
     }
 
-    pub fn check_lock_status(ctx: Context<CheckLockStatus>) -> Result<LockStatus> {
+    /// 關閉已全數釋放的鎖倉部位，租金退回用戶
+    pub fn close_lock_record(ctx: Context<CloseLockRecord>, lock_id: u64) -> Result<()> {
+        ctx.accounts.lock_index.close_position(lock_id);
+
+        emit!(LockClosedEvent {
+            user: ctx.accounts.user.key(),
+            lock_id,
+        });
+
+        Ok(())
+    }
+
+    pub fn check_lock_status(ctx: Context<CheckLockStatus>, _lock_id: u64) -> Result<LockStatus> {
         let lock_record = &ctx.accounts.lock_record;
         let current_time = Clock::get()?.unix_timestamp;
 
@@ -124,8 +181,12 @@ pub mod lock_manager {
             0
         };
 
-        let days_since_start = (current_time - lock_record.start_time) / 86400;
-        let redeemable_amount = lock_record.daily_release.saturating_mul(days_since_start as u64).min(lock_record.amount);
+        let redeemable_amount = if is_locked {
+            let days_since_start = (current_time - lock_record.start_time) / 86400;
+            lock_record.daily_release.saturating_mul(days_since_start as u64).min(lock_record.amount)
+        } else {
+            lock_record.amount
+        };
 
         let redemption_deadline = lock_record.start_time + ((lock_record.lock_period as i64 + 14) * 86400);
 
@@ -137,7 +198,7 @@ pub mod lock_manager {
         })
     }
 
-    pub fn is_within_redemption_window(ctx: Context<CheckRedemptionWindow>) -> Result<bool> {
+    pub fn is_within_redemption_window(ctx: Context<CheckRedemptionWindow>, lock_id: u64) -> Result<bool> {
         let lock_record = &ctx.accounts.lock_record;
         let current_time = Clock::get()?.unix_timestamp;
 
@@ -153,6 +214,7 @@ pub mod lock_manager {
         // 發出事件以記錄檢查結果
         emit!(RedemptionWindowCheckEvent {
             user: ctx.accounts.user.key(),
+            lock_id,
             is_within_window,
             current_time,
             lock_end_time,
@@ -183,17 +245,31 @@ pub struct LockXxUSD<'info> {
     /// CHECK: This is the LockManager PDA
     #[account(seeds = [b"lock_manager"], bump)]
     pub lock_manager: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 8 + 4 + 8 * MAX_OPEN_LOCKS,
+        seeds = [b"lock_index", user.key().as_ref()],
+        bump
+    )]
+    pub lock_index: Account<'info, UserLockIndex>,
     #[account(
         init,
         payer = user,
-        space = 8 + 32 + 8 + 8 + 8 + 8 + 8,
-        seeds = [b"lock_record", user.key().as_ref()],
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8,
+        seeds = [b"lock_record", user.key().as_ref(), &lock_index.next_id.to_le_bytes()],
         bump
     )]
     pub lock_record: Account<'info, LockRecord>,
     /// CHECK: This is the AssetManager program
     #[account(constraint = asset_manager.key() == ASSET_MANAGER_PROGRAM_ID @ LockManagerError::InvalidAssetManager)]
     pub asset_manager: AccountInfo<'info>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
@@ -201,6 +277,69 @@ pub struct LockXxUSD<'info> {
 }
 
 #[derive(Accounts)]
+pub struct LockMintedXxUSD<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub xxusd_mint: Account<'info, token::Mint>,
+    #[account(
+        mut,
+        associated_token::mint = xxusd_mint,
+        associated_token::authority = source_authority,
+    )]
+    pub source: Account<'info, TokenAccount>,
+    /// 必須是 asset_manager 以其狀態帳戶推導出的 PDA
+    #[account(
+        seeds = [asset_manager_state.key().as_ref()],
+        bump,
+        seeds::program = ASSET_MANAGER_PROGRAM_ID,
+    )]
+    pub source_authority: Signer<'info>,
+    /// CHECK: AssetManager 唯一的狀態帳戶，只用於推導 source_authority
+    #[account(
+        owner = ASSET_MANAGER_PROGRAM_ID @ LockManagerError::InvalidAssetManager,
+        seeds = [b"state"],
+        bump,
+        seeds::program = ASSET_MANAGER_PROGRAM_ID,
+    )]
+    pub asset_manager_state: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = xxusd_mint,
+        associated_token::authority = lock_manager,
+    )]
+    pub lock_vault: Account<'info, TokenAccount>,
+    /// CHECK: This is the LockManager PDA
+    #[account(seeds = [b"lock_manager"], bump)]
+    pub lock_manager: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 8 + 4 + 8 * MAX_OPEN_LOCKS,
+        seeds = [b"lock_index", user.key().as_ref()],
+        bump
+    )]
+    pub lock_index: Account<'info, UserLockIndex>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8,
+        seeds = [b"lock_record", user.key().as_ref(), &lock_index.next_id.to_le_bytes()],
+        bump
+    )]
+    pub lock_record: Account<'info, LockRecord>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: u64)]
 pub struct ReleaseDailyXxUSD<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
@@ -222,20 +361,58 @@ pub struct ReleaseDailyXxUSD<'info> {
     pub lock_manager: AccountInfo<'info>,
     #[account(
         mut,
-        seeds = [b"lock_record", user.key().as_ref()],
+        seeds = [b"lock_record", user.key().as_ref(), &lock_id.to_le_bytes()],
         bump,
+        constraint = lock_record.mint == xxusd_mint.key() @ LockManagerError::InvalidMint,
     )]
     pub lock_record: Account<'info, LockRecord>,
+    #[account(
+        mut,
+        seeds = [b"lock_index", user.key().as_ref()],
+        bump,
+    )]
+    pub lock_index: Account<'info, UserLockIndex>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
     pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
+#[instruction(lock_id: u64)]
+pub struct CloseLockRecord<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"lock_record", user.key().as_ref(), &lock_id.to_le_bytes()],
+        bump,
+        constraint = lock_record.owner == user.key() @ LockManagerError::InvalidOwner,
+        constraint = lock_record.mint == xxusd_mint.key() @ LockManagerError::InvalidMint,
+        constraint = lock_record.amount == 0 @ LockManagerError::LockNotFinished,
+    )]
+    pub lock_record: Account<'info, LockRecord>,
+    pub xxusd_mint: Account<'info, token::Mint>,
+    #[account(
+        mut,
+        seeds = [b"lock_index", user.key().as_ref()],
+        bump,
+    )]
+    pub lock_index: Account<'info, UserLockIndex>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: u64)]
 pub struct CheckLockStatus<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     #[account(
         mut,
-        seeds = [b"lock_record", user.key().as_ref()],
+        seeds = [b"lock_record", user.key().as_ref(), &lock_id.to_le_bytes()],
         bump,
         constraint = lock_record.owner == user.key() @ LockManagerError::InvalidOwner,
     )]
@@ -243,20 +420,47 @@ pub struct CheckLockStatus<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(lock_id: u64)]
 pub struct CheckRedemptionWindow<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     #[account(
-        seeds = [b"lock_record", user.key().as_ref()],
+        seeds = [b"lock_record", user.key().as_ref(), &lock_id.to_le_bytes()],
         bump,
         constraint = lock_record.owner == user.key() @ LockManagerError::InvalidOwner,
     )]
     pub lock_record: Account<'info, LockRecord>,
 }
 
+/// 用戶的鎖倉索引：下一個部位編號與尚未結束的部位
+#[account]
+pub struct UserLockIndex {
+    pub owner: Pubkey,
+    pub next_id: u64,
+    pub open_ids: Vec<u64>,
+}
+
+impl UserLockIndex {
+    fn open_position(&mut self, owner: Pubkey) -> Result<u64> {
+        require!(self.open_ids.len() < MAX_OPEN_LOCKS, LockManagerError::TooManyOpenLocks);
+        self.owner = owner;
+        let lock_id = self.next_id;
+        self.next_id = self.next_id.checked_add(1).ok_or(LockManagerError::InvalidLockParameters)?;
+        self.open_ids.push(lock_id);
+        Ok(lock_id)
+    }
+
+    fn close_position(&mut self, lock_id: u64) {
+        self.open_ids.retain(|id| *id != lock_id);
+    }
+}
+
 #[account]
 pub struct LockRecord {
     pub owner: Pubkey,
+    // 開立部位時鎖入的代幣，釋放與關閉只接受同一個 mint
+    pub mint: Pubkey,
+    pub id: u64,
     pub amount: u64,
     pub lock_period: u64,
     pub daily_release: u64,
@@ -264,6 +468,39 @@ pub struct LockRecord {
     pub last_release_time: i64,
 }
 
+impl LockRecord {
+    fn open(
+        &mut self,
+        owner: Pubkey,
+        mint: Pubkey,
+        id: u64,
+        amount: u64,
+        lock_period: u64,
+        daily_release: u64,
+    ) -> Result<()> {
+        self.owner = owner;
+        self.mint = mint;
+        self.id = id;
+        self.amount = amount;
+        self.lock_period = lock_period;
+        self.daily_release = daily_release;
+        self.start_time = Clock::get()?.unix_timestamp;
+        self.last_release_time = self.start_time;
+        Ok(())
+    }
+}
+
+fn validate_lock_params(amount: u64, lock_period: u64, daily_release: u64) -> Result<()> {
+    require!(amount > 0, LockManagerError::InvalidAmount);
+    require!(lock_period > 0, LockManagerError::InvalidLockPeriod);
+    require!(daily_release > 0, LockManagerError::InvalidDailyRelease);
+    let total_release = daily_release
+        .checked_mul(lock_period)
+        .ok_or(LockManagerError::InvalidLockParameters)?;
+    require!(amount >= total_release, LockManagerError::InvalidLockParameters);
+    Ok(())
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
 pub struct LockStatus {
     pub is_locked: bool,
@@ -290,15 +527,22 @@ pub enum LockManagerError {
     NoAmountToRelease,
     #[msg("Invalid owner")]
     InvalidOwner,
-    #[msg("Lock period has ended")]
-    LockPeriodEnded,
     #[msg("Already released today")]
     AlreadyReleasedToday,
+    #[msg("System is paused")]
+    SystemPaused,
+    #[msg("Too many open lock positions")]
+    TooManyOpenLocks,
+    #[msg("Lock still holds xxUSD")]
+    LockNotFinished,
+    #[msg("Mint does not match the locked token")]
+    InvalidMint,
 }
 
 #[event]
 pub struct LockEvent {
     pub user: Pubkey,
+    pub lock_id: u64,
     pub amount: u64,
     pub lock_period: u64,
     pub daily_release: u64,
@@ -307,17 +551,28 @@ pub struct LockEvent {
 #[event]
 pub struct ReleaseEvent {
     pub user: Pubkey,
+    pub lock_id: u64,
     pub amount: u64,
 }
 
+#[event]
+pub struct LockClosedEvent {
+    pub user: Pubkey,
+    pub lock_id: u64,
+}
+
 #[event]
 pub struct RedemptionWindowCheckEvent {
     pub user: Pubkey,
+    pub lock_id: u64,
     pub is_within_window: bool,
     pub current_time: i64,
     pub lock_end_time: i64,
     pub redemption_end_time: i64,
 }
 
-// 替換為實際的 AssetManager 程序 ID
-pub const ASSET_MANAGER_PROGRAM_ID: Pubkey = solana_program::pubkey!("91hM5ZdHVbH7tH1a21QHRmPEFkHWS532DfcpGPBUkdAF");
\ No newline at end of file
+// 每位用戶可同時持有的鎖倉部位上限
+pub const MAX_OPEN_LOCKS: usize = 16;
+
+// AssetManager 依賴本程式做 CPI，為避免循環依賴在此直接寫入其程式 ID
+pub const ASSET_MANAGER_PROGRAM_ID: Pubkey = solana_program::pubkey!("HiudtiK3QwUEiRWU3WB7h1azKvu7kpwbZyU1qBvaUUD6");
\ No newline at end of file
@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+
+declare_id!("8ZSnxmM1kSf2f4biaoa6ZmfbrqiZxyh4pWXXiW5LPUNX");
+
+/// 標籤長度上限，與 price_oracle 的資產代號一致
+pub const MAX_LABEL_LEN: usize = 16;
+/// PullFeedAccountData 最多保存的 oracle 提交數
+const MAX_MOCK_SAMPLES: usize = 32;
+
+/// 僅供本地驗證器與 program-test 使用：由 authority 直接寫入、版面與 Switchboard 相同的模擬 feed。
+/// price_oracle 只有在啟用 mock-oracle feature 時才接受本程式擁有的 feed。
+#[program]
+pub mod mock_oracle {
+    use super::*;
+
+    pub fn init_mock_feed(ctx: Context<InitMockFeed>, label: String) -> Result<()> {
+        require!(!label.is_empty() && label.len() <= MAX_LABEL_LEN, MockOracleError::InvalidLabel);
+
+        let authority_key = ctx.accounts.authority.key();
+        let seeds: &[&[u8]] = &[b"mock_feed", authority_key.as_ref(), label.as_bytes(), &[ctx.bumps.mock_feed]];
+        let space = 8 + std::mem::size_of::<PullFeedAccountData>();
+
+        anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::CreateAccount {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.mock_feed.to_account_info(),
+                },
+                &[seeds],
+            ),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID,
+        )?;
+
+        let mut data = ctx.accounts.mock_feed.try_borrow_mut_data()?;
+        data[..8].copy_from_slice(&PullFeedAccountData::discriminator());
+
+        msg!("Mock feed {} created at {}", label, ctx.accounts.mock_feed.key());
+
+        Ok(())
+    }
+
+    pub fn set_mock_feed(ctx: Context<SetMockFeed>, label: String, value: i128, std_dev: i128, samples: u8) -> Result<()> {
+        require!((samples as usize) <= MAX_MOCK_SAMPLES, MockOracleError::TooManySamples);
+
+        let clock = Clock::get()?;
+
+        // 以 PullFeedAccountData 的版面寫入，讓 price_oracle 無需區分模擬與真實 feed
+        let mut feed = bytemuck::zeroed_box::<PullFeedAccountData>();
+        feed.authority = ctx.accounts.authority.key();
+        feed.last_update_timestamp = clock.unix_timestamp;
+        feed.min_sample_size = samples;
+        feed.result.value = value;
+        feed.result.std_dev = std_dev;
+        feed.result.mean = value;
+        feed.result.min_value = value;
+        feed.result.max_value = value;
+        feed.result.slot = clock.slot;
+        feed.result.min_slot = clock.slot;
+        feed.result.max_slot = clock.slot;
+        for submission in feed.submissions.iter_mut().take(samples as usize) {
+            submission.slot = clock.slot;
+            submission.value = value;
+        }
+
+        let mut data = ctx.accounts.mock_feed.try_borrow_mut_data()?;
+        require!(data[..8] == PullFeedAccountData::discriminator(), MockOracleError::FeedMismatch);
+        data[8..].copy_from_slice(bytemuck::bytes_of(feed.as_ref()));
+
+        msg!("Mock feed {} set to {} (std dev {}, {} samples)", label, value, std_dev, samples);
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct InitMockFeed<'info> {
+    /// CHECK: Created here with the PullFeedAccountData layout and owned by this program
+    #[account(
+        mut,
+        seeds = [b"mock_feed", authority.key().as_ref(), label.as_bytes()],
+        bump
+    )]
+    pub mock_feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct SetMockFeed<'info> {
+    /// CHECK: Mock feed created by init_mock_feed; the discriminator is verified before writing
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"mock_feed", authority.key().as_ref(), label.as_bytes()],
+        bump
+    )]
+    pub mock_feed: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[error_code]
+pub enum MockOracleError {
+    #[msg("Invalid Mock Feed Label")]
+    InvalidLabel,
+    #[msg("Too Many Mock Samples")]
+    TooManySamples,
+    #[msg("Account Is Not A Mock Feed")]
+    FeedMismatch,
+}
@@ -1,11 +1,25 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock::Clock;
 use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
 use switchboard_on_demand::prelude::rust_decimal::prelude::ToPrimitive;
-use std::str::FromStr;
+use switchboard_on_demand::prelude::rust_decimal::Decimal;
 
 declare_id!("JDwoM6dhTx2fFDk47ytmjzotpNqsb92ctofKr9jbH1W3");
 
+/// Switchboard 結果以 18 位小數的 i128 表示
+const SWITCHBOARD_DECIMALS: u32 = 18;
+/// LST 價格以 9 位小數的定點數儲存
+pub const LST_PRICE_DECIMALS: u32 = 9;
+/// LST APY 以基點（1% = 100 bps）儲存
+pub const APY_BPS_PER_PERCENT: u64 = 100;
+pub const MAX_SYMBOL_LEN: usize = 16;
+const MAX_FEED_DECIMALS: u8 = 18;
+const BPS_DENOMINATOR: u64 = 10_000;
+pub const MAX_FALLBACK_FEEDS: usize = 3;
+/// 每個資產保留的價格觀測數量
+pub const MAX_OBSERVATIONS: usize = 32;
+
 #[program]
 pub mod price_oracle {
     use super::*;
@@ -14,142 +28,1103 @@ pub mod price_oracle {
         let oracle_account = &mut ctx.accounts.oracle_account;
         oracle_account.authority = *ctx.accounts.authority.key;
 
-        // 初始化 SOL 價格數據源
-        oracle_account.sol_feed = Pubkey::from_str("GvDMxPzN1sCj7L26YDK2HnMRXEQmQ2aemov8YBtPS7vR")
-            .map_err(|_| PriceOracleError::InvalidFeedKey)?;
-        oracle_account.last_update_timestamp_sol = 0;
-        oracle_account.cached_price_sol = 0;
+        msg!("Oracle initialized. Register data sources with add_feed and add_lst_asset.");
+
+        Ok(())
+    }
+
+    pub fn add_feed(
+        ctx: Context<AddFeed>,
+        symbol: String,
+        max_staleness: i64,
+        decimals: u8,
+        guards: FeedGuards,
+    ) -> Result<()> {
+        require!(
+            !symbol.is_empty() && symbol.len() <= MAX_SYMBOL_LEN,
+            PriceOracleError::InvalidSymbol
+        );
+        validate_feed_params(max_staleness, decimals)?;
+        guards.validate()?;
+
+        let feed_config = &mut ctx.accounts.feed_config;
+        feed_config.oracle = ctx.accounts.oracle_account.key();
+        feed_config.symbol = symbol.clone();
+        feed_config.feed = ctx.accounts.feed.key();
+        feed_config.max_staleness = max_staleness;
+        feed_config.decimals = decimals;
+        feed_config.guards = guards;
+        feed_config.fallback_feeds = Vec::new();
+        feed_config.manual_price = 0;
+        feed_config.manual_price_timestamp = 0;
+        feed_config.manual_max_age = 0;
+        feed_config.max_deviation_bps = 0;
+        feed_config.deviation_flagged = false;
+        feed_config.cached_price = 0;
+        feed_config.last_update_timestamp = 0;
+        feed_config.bump = ctx.bumps.feed_config;
 
-        // 初始化 InterestAsset 的數據源
-        oracle_account.interest_asset_feed = Pubkey::from_str("4NiWaTuje7SVe9DN1vfnX7m1qBC7DnUxwRxbdgEDUGX1")
-            .map_err(|_| PriceOracleError::InvalidFeedKey)?;
-        oracle_account.last_update_timestamp_interest_asset = 0;
+        let price_history = &mut ctx.accounts.price_history;
+        price_history.oracle = feed_config.oracle;
+        price_history.source = feed_config.key();
+        price_history.symbol = feed_config.symbol.clone();
+        price_history.head = 0;
+        price_history.observations = Vec::new();
+        price_history.max_move_bps = 0;
+        price_history.stabilization_updates = 0;
+        price_history.halted = false;
+        price_history.stable_updates = 0;
+        price_history.bump = ctx.bumps.price_history;
 
-        msg!("Oracle initialized with SOL feed account and Interest Asset feed account.");
+        emit!(FeedRegisteredEvent {
+            symbol,
+            feed: feed_config.feed,
+            max_staleness,
+            decimals,
+        });
+
+        msg!("Feed registered for {}: {}", feed_config.symbol, feed_config.feed);
 
         Ok(())
     }
 
-    pub fn get_price(ctx: Context<GetPrice>, asset: String) -> Result<()> {
-        let oracle_account = &mut ctx.accounts.oracle_account;
+    pub fn update_feed(ctx: Context<UpdateFeed>, max_staleness: i64, decimals: u8, guards: FeedGuards) -> Result<()> {
+        validate_feed_params(max_staleness, decimals)?;
+        guards.validate()?;
+
+        let feed_config = &mut ctx.accounts.feed_config;
+        require!(
+            !feed_config.fallback_feeds.contains(&ctx.accounts.feed.key()),
+            PriceOracleError::DuplicateFeed
+        );
+        feed_config.feed = ctx.accounts.feed.key();
+        feed_config.max_staleness = max_staleness;
+        feed_config.decimals = decimals;
+        feed_config.guards = guards;
+        // 快取價格來自舊的 feed 與小數位數，必須重新讀取
+        feed_config.cached_price = 0;
+        feed_config.last_update_timestamp = 0;
+
+        emit!(FeedRegisteredEvent {
+            symbol: feed_config.symbol.clone(),
+            feed: feed_config.feed,
+            max_staleness,
+            decimals,
+        });
+
+        msg!("Feed updated for {}: {}", feed_config.symbol, feed_config.feed);
+
+        Ok(())
+    }
+
+    pub fn remove_feed(ctx: Context<RemoveFeed>) -> Result<()> {
+        emit!(FeedRemovedEvent {
+            symbol: ctx.accounts.feed_config.symbol.clone(),
+            feed: ctx.accounts.feed_config.feed,
+        });
+
+        msg!("Feed removed for {}", ctx.accounts.feed_config.symbol);
+
+        Ok(())
+    }
+
+    pub fn set_fallback_sources(
+        ctx: Context<SetFallbackSources>,
+        fallback_feeds: Vec<Pubkey>,
+        manual_max_age: i64,
+        max_deviation_bps: u64,
+    ) -> Result<()> {
+        let feed_config = &mut ctx.accounts.feed_config;
+
+        require!(fallback_feeds.len() <= MAX_FALLBACK_FEEDS, PriceOracleError::TooManyFallbackFeeds);
+        for (i, feed) in fallback_feeds.iter().enumerate() {
+            require!(*feed != feed_config.feed, PriceOracleError::DuplicateFeed);
+            require!(!fallback_feeds[..i].contains(feed), PriceOracleError::DuplicateFeed);
+        }
+        require!(manual_max_age >= 0, PriceOracleError::InvalidStaleness);
+        require!(
+            max_deviation_bps > 0 && max_deviation_bps <= BPS_DENOMINATOR,
+            PriceOracleError::InvalidDeviationThreshold
+        );
+
+        feed_config.fallback_feeds = fallback_feeds;
+        feed_config.manual_max_age = manual_max_age;
+        feed_config.max_deviation_bps = max_deviation_bps;
+
+        emit!(FallbackSourcesUpdatedEvent {
+            symbol: feed_config.symbol.clone(),
+            fallback_feeds: feed_config.fallback_feeds.clone(),
+            manual_max_age,
+            max_deviation_bps,
+        });
+
+        msg!("Fallback sources updated for {}: {} feeds", feed_config.symbol, feed_config.fallback_feeds.len());
+
+        Ok(())
+    }
+
+    pub fn push_manual_price(ctx: Context<PushManualPrice>, price: u64) -> Result<()> {
+        require!(price > 0, PriceOracleError::ZeroPrice);
+
+        let feed_config = &mut ctx.accounts.feed_config;
+        feed_config.manual_price = price;
+        feed_config.manual_price_timestamp = Clock::get()?.unix_timestamp;
+
+        emit!(ManualPricePushedEvent {
+            symbol: feed_config.symbol.clone(),
+            price,
+            timestamp: feed_config.manual_price_timestamp,
+        });
+
+        msg!("Manual price pushed for {}: {}", feed_config.symbol, price);
 
-        // 獲取當前時鐘
+        Ok(())
+    }
+
+    pub fn add_lst_asset(
+        ctx: Context<AddLstAsset>,
+        symbol: String,
+        max_staleness: i64,
+        guards: FeedGuards,
+    ) -> Result<()> {
+        require!(
+            !symbol.is_empty() && symbol.len() <= MAX_SYMBOL_LEN,
+            PriceOracleError::InvalidSymbol
+        );
+        require!(max_staleness > 0, PriceOracleError::InvalidStaleness);
+        guards.validate()?;
+
+        let lst_asset = &mut ctx.accounts.lst_asset;
+        lst_asset.oracle = ctx.accounts.oracle_account.key();
+        lst_asset.mint = ctx.accounts.mint.key();
+        lst_asset.symbol = symbol;
+        lst_asset.price_feed = ctx.accounts.price_feed.key();
+        lst_asset.apy_feed = ctx.accounts.apy_feed.key();
+        lst_asset.max_staleness = max_staleness;
+        lst_asset.guards = guards;
+        lst_asset.price = 0;
+        lst_asset.apy = 0;
+        lst_asset.last_update_timestamp = 0;
+        lst_asset.bump = ctx.bumps.lst_asset;
+
+        let price_history = &mut ctx.accounts.price_history;
+        price_history.oracle = lst_asset.oracle;
+        price_history.source = lst_asset.key();
+        price_history.symbol = lst_asset.symbol.clone();
+        price_history.head = 0;
+        price_history.observations = Vec::new();
+        price_history.max_move_bps = 0;
+        price_history.stabilization_updates = 0;
+        price_history.halted = false;
+        price_history.stable_updates = 0;
+        price_history.bump = ctx.bumps.price_history;
+
+        emit!(LstAssetRegisteredEvent {
+            mint: lst_asset.mint,
+            symbol: lst_asset.symbol.clone(),
+            price_feed: lst_asset.price_feed,
+            apy_feed: lst_asset.apy_feed,
+            max_staleness,
+        });
+
+        msg!("LST asset registered: {} ({})", lst_asset.symbol, lst_asset.mint);
+
+        Ok(())
+    }
+
+    pub fn update_lst_asset(ctx: Context<UpdateLstAsset>, max_staleness: i64, guards: FeedGuards) -> Result<()> {
+        require!(max_staleness > 0, PriceOracleError::InvalidStaleness);
+        guards.validate()?;
+
+        let lst_asset = &mut ctx.accounts.lst_asset;
+        lst_asset.price_feed = ctx.accounts.price_feed.key();
+        lst_asset.apy_feed = ctx.accounts.apy_feed.key();
+        lst_asset.max_staleness = max_staleness;
+        lst_asset.guards = guards;
+        // 數據源已更換，舊快取不再可信
+        lst_asset.last_update_timestamp = 0;
+
+        emit!(LstAssetRegisteredEvent {
+            mint: lst_asset.mint,
+            symbol: lst_asset.symbol.clone(),
+            price_feed: lst_asset.price_feed,
+            apy_feed: lst_asset.apy_feed,
+            max_staleness,
+        });
+
+        msg!("LST asset updated: {} ({})", lst_asset.symbol, lst_asset.mint);
+
+        Ok(())
+    }
+
+    pub fn remove_lst_asset(ctx: Context<RemoveLstAsset>) -> Result<()> {
+        emit!(LstAssetRemovedEvent {
+            mint: ctx.accounts.lst_asset.mint,
+            symbol: ctx.accounts.lst_asset.symbol.clone(),
+        });
+
+        msg!("LST asset removed: {}", ctx.accounts.lst_asset.symbol);
+
+        Ok(())
+    }
+
+    pub fn refresh_lst_asset(ctx: Context<RefreshLstAsset>) -> Result<()> {
+        let lst_asset = &mut ctx.accounts.lst_asset;
         let clock = Clock::get()?;
 
+        if clock.unix_timestamp - lst_asset.last_update_timestamp <= lst_asset.max_staleness {
+            msg!("Returning cached {} data: price {}, APY {} bps", lst_asset.symbol, lst_asset.price, lst_asset.apy);
+            return Ok(());
+        }
+
+        let price = load_checked_feed(&ctx.accounts.price_feed, &lst_asset.guards, &clock)?
+            .value()
+            .ok_or(PriceOracleError::PriceFetchFailed)?;
+        let apy = load_checked_feed(&ctx.accounts.apy_feed, &lst_asset.guards, &clock)?
+            .value()
+            .ok_or(PriceOracleError::PriceFetchFailed)?;
+
+        let price = decimal_to_fixed(price, 10u64.pow(LST_PRICE_DECIMALS))?;
+        require!(price > 0, PriceOracleError::ZeroPrice);
+
+        lst_asset.price = price;
+        lst_asset.apy = decimal_to_fixed(apy, APY_BPS_PER_PERCENT)?;
+        lst_asset.last_update_timestamp = clock.unix_timestamp;
+        ctx.accounts.price_history.record(clock.unix_timestamp, lst_asset.price)?;
+
+        msg!("New {} data fetched and cached: price {}, APY {} bps", lst_asset.symbol, lst_asset.price, lst_asset.apy);
+
+        Ok(())
+    }
+
+    pub fn get_price(ctx: Context<GetPrice>, asset: String) -> Result<()> {
+        let oracle_account = &ctx.accounts.oracle_account;
+
         // 檢查是否初始化
         require!(
             oracle_account.authority != Pubkey::default(),
             PriceOracleError::NotInitialized
         );
+        require!(ctx.accounts.feed_config.symbol == asset, PriceOracleError::InvalidAsset);
 
-        match asset.as_str() {
-            "SOL" => {
-                if clock.unix_timestamp - oracle_account.last_update_timestamp_sol <= 60 {
-                    msg!("Returning cached SOL price: {}", oracle_account.cached_price_sol);
-                    return Ok(());
-                }
+        let feed_config_key = ctx.accounts.feed_config.key();
+        refresh_feed_price(
+            &mut ctx.accounts.feed_config,
+            feed_config_key,
+            &ctx.accounts.feed,
+            ctx.remaining_accounts,
+            &mut ctx.accounts.price_history,
+            &Clock::get()?,
+        )?;
 
-                let feed_account = ctx.accounts.sol_feed.data.borrow();
-                let feed = PullFeedAccountData::parse(feed_account)
-                    .map_err(|_| PriceOracleError::PriceFetchFailed)?;
-                let price = feed.value().ok_or(PriceOracleError::PriceFetchFailed)?;
+        Ok(())
+    }
 
-                let price_u64 = price.to_u64().ok_or(PriceOracleError::PriceConversionFailed)?;
+    /// 以與 get_price 相同的彙整流程取得價格，並拒絕熔斷中或來源偏離過大的價格
+    pub fn get_price_data(ctx: Context<GetPriceData>, asset: String) -> Result<PriceData> {
+        let oracle_account = &ctx.accounts.oracle_account;
 
-                require!(price_u64 > 0, PriceOracleError::ZeroPrice);
+        // 檢查是否初始化
+        require!(
+            oracle_account.authority != Pubkey::default(),
+            PriceOracleError::NotInitialized
+        );
+        require!(ctx.accounts.feed_config.symbol == asset, PriceOracleError::InvalidAsset);
 
-                oracle_account.cached_price_sol = price_u64;
-                oracle_account.last_update_timestamp_sol = clock.unix_timestamp;
+        let feed_config_key = ctx.accounts.feed_config.key();
+        let (price, source) = refresh_feed_price(
+            &mut ctx.accounts.feed_config,
+            feed_config_key,
+            &ctx.accounts.feed,
+            ctx.remaining_accounts,
+            &mut ctx.accounts.price_history,
+            &Clock::get()?,
+        )?;
+        require!(!ctx.accounts.price_history.halted, PriceOracleError::PriceHalted);
 
-                msg!("New SOL price fetched and cached: {}", price_u64);
-            }
-            "InterestAsset" => {
-                if clock.unix_timestamp - oracle_account.last_update_timestamp_interest_asset <= 60 {
-                    msg!("Returning cached Interest Asset data");
-                    return Ok(());
-                }
-
-                let feed_account = ctx.accounts.interest_asset_feed.data.borrow();
-                let feed = PullFeedAccountData::parse(feed_account)
-                    .map_err(|_| PriceOracleError::PriceFetchFailed)?;
-                let result = feed.value().ok_or(PriceOracleError::PriceFetchFailed)?;
-
-                let result_str = result.to_string();
-                let result_parts: Vec<&str> = result_str.split(',').collect();
-
-                if result_parts.len() != 13 {
-                    return Err(PriceOracleError::InvalidDataFormat.into());
-                }
-
-                oracle_account.jupsol_price = result_parts[0].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.jupsol_apy = result_parts[1].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.vsol_price = result_parts[2].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.vsol_apy = result_parts[3].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.bsol_price = result_parts[4].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.bsol_apy = result_parts[5].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.msol_price = result_parts[6].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.msol_apy = result_parts[7].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.hsol_price = result_parts[8].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.hsol_apy = result_parts[9].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.jitosol_price = result_parts[10].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-                oracle_account.jitosol_apy = result_parts[11].parse().map_err(|_| PriceOracleError::PriceConversionFailed)?;
-
-                oracle_account.last_update_timestamp_interest_asset = clock.unix_timestamp;
-
-                msg!("New Interest Asset data fetched and cached");
-            }
-            _ => {
-                return Err(PriceOracleError::InvalidAsset.into());
+        let feed_config = &ctx.accounts.feed_config;
+        require!(!feed_config.deviation_flagged, PriceOracleError::PriceDeviationFlagged);
+
+        // 彙整後的價格沒有單一來源的信賴區間，與 get_price 相同以 feed_config.decimals 位小數表示
+        let price_data = PriceData {
+            price,
+            exponent: -(feed_config.decimals as i32),
+            confidence: 0,
+            publish_time: feed_config.last_update_timestamp,
+            source,
+        };
+
+        msg!("Price data for {}: {} (exponent {})", asset, price_data.price, price_data.exponent);
+
+        Ok(price_data)
+    }
+
+    pub fn configure_circuit_breaker(
+        ctx: Context<ManageCircuitBreaker>,
+        max_move_bps: u64,
+        stabilization_updates: u8,
+    ) -> Result<()> {
+        require!(max_move_bps <= BPS_DENOMINATOR, PriceOracleError::InvalidDeviationThreshold);
+
+        let price_history = &mut ctx.accounts.price_history;
+        price_history.max_move_bps = max_move_bps;
+        price_history.stabilization_updates = stabilization_updates;
+
+        msg!(
+            "Circuit breaker for {}: max move {} bps, {} stable updates to resume",
+            price_history.symbol,
+            max_move_bps,
+            stabilization_updates
+        );
+
+        Ok(())
+    }
+
+    pub fn acknowledge_halt(ctx: Context<ManageCircuitBreaker>) -> Result<()> {
+        let price_history = &mut ctx.accounts.price_history;
+        require!(price_history.halted, PriceOracleError::NotHalted);
+
+        price_history.halted = false;
+        price_history.stable_updates = 0;
+
+        emit!(CircuitBreakerResetEvent {
+            symbol: price_history.symbol.clone(),
+            acknowledged: true,
+        });
+
+        msg!("Halt acknowledged for {}", price_history.symbol);
+
+        Ok(())
+    }
+
+    pub fn get_twap(ctx: Context<GetTwap>, asset: String, window_secs: i64) -> Result<u64> {
+        let price_history = &ctx.accounts.price_history;
+        require!(price_history.symbol == asset, PriceOracleError::FeedMismatch);
+
+        let twap = price_history.twap(Clock::get()?.unix_timestamp, window_secs)?;
+
+        msg!("TWAP for {} over {} seconds: {}", asset, window_secs, twap);
+
+        Ok(twap)
+    }
+
+    pub fn get_lst_price_data(ctx: Context<GetLstPriceData>) -> Result<PriceData> {
+        let lst_asset = &ctx.accounts.lst_asset;
+        require!(lst_asset.price > 0, PriceOracleError::ZeroPrice);
+
+        let price_data = PriceData {
+            price: lst_asset.price,
+            exponent: -(LST_PRICE_DECIMALS as i32),
+            confidence: 0,
+            publish_time: lst_asset.last_update_timestamp,
+            source: PriceSource::Cache,
+        };
+
+        msg!("Price data for {}: {} (exponent {})", lst_asset.symbol, price_data.price, price_data.exponent);
+
+        Ok(price_data)
+    }
+}
+
+/// 快取未超過 max_staleness 時直接沿用，否則彙整主 feed、備援 feed 與手動價格取中位數，
+/// 標記偏離過大的來源並寫入快取與價格紀錄
+fn refresh_feed_price(
+    feed_config: &mut FeedConfig,
+    feed_config_key: Pubkey,
+    primary_feed: &AccountInfo,
+    fallback_accounts: &[AccountInfo],
+    price_history: &mut PriceHistory,
+    clock: &Clock,
+) -> Result<(u64, PriceSource)> {
+    if feed_config.cached_price > 0
+        && clock.unix_timestamp - feed_config.last_update_timestamp <= feed_config.max_staleness
+    {
+        msg!("Returning cached {} price: {}", feed_config.symbol, feed_config.cached_price);
+        return Ok((feed_config.cached_price, PriceSource::Cache));
+    }
+
+    // 備援 feed 必須依註冊順序全部提供，避免呼叫者挑選對自己有利的來源
+    require!(
+        fallback_accounts.len() == feed_config.fallback_feeds.len(),
+        PriceOracleError::FeedMismatch
+    );
+
+    let mut fallback_prices = Vec::new();
+    for (account, expected) in fallback_accounts.iter().zip(feed_config.fallback_feeds.iter()) {
+        require_keys_eq!(account.key(), *expected, PriceOracleError::FeedMismatch);
+        match read_feed_price(account, feed_config, clock) {
+            Ok(price) => fallback_prices.push((account.key(), price)),
+            Err(err) => msg!("Fallback feed {} rejected: {}", account.key(), err),
+        }
+    }
+
+    // 手動價格以 feed_config 帳戶作為來源標識
+    if feed_config.manual_price > 0
+        && clock.unix_timestamp - feed_config.manual_price_timestamp <= feed_config.manual_max_age
+    {
+        fallback_prices.push((feed_config_key, feed_config.manual_price));
+    }
+
+    let (sources, used_fallback) = match read_feed_price(primary_feed, feed_config, clock) {
+        Ok(price) => {
+            let mut sources = vec![(primary_feed.key(), price)];
+            sources.extend(fallback_prices);
+            (sources, false)
+        }
+        Err(err) => {
+            msg!("Primary {} feed rejected: {}", feed_config.symbol, err);
+            if fallback_prices.is_empty() {
+                return Err(err);
             }
+            (fallback_prices, true)
         }
+    };
 
-        Ok(())
+    let price_u64 = median_price(&sources)?;
+    feed_config.deviation_flagged =
+        flag_deviations(&feed_config.symbol, &sources, price_u64, feed_config.max_deviation_bps)?;
+
+    if used_fallback {
+        emit!(FallbackUsedEvent {
+            symbol: feed_config.symbol.clone(),
+            price: price_u64,
+            sources_used: sources.len() as u8,
+        });
+    }
+
+    feed_config.cached_price = price_u64;
+    feed_config.last_update_timestamp = clock.unix_timestamp;
+    price_history.record(clock.unix_timestamp, price_u64)?;
+
+    msg!("New {} price fetched and cached: {} ({} sources)", feed_config.symbol, price_u64, sources.len());
+
+    Ok((price_u64, PriceSource::Switchboard))
+}
+
+/// 讀取單一 feed 並依 feed_config 的小數位數轉為定點價格
+fn read_feed_price(feed: &AccountInfo, feed_config: &FeedConfig, clock: &Clock) -> Result<u64> {
+    let feed = load_checked_feed(feed, &feed_config.guards, clock)?;
+    let price_u64 = scale_feed_value(feed.result.value, feed_config.decimals)?;
+
+    require!(price_u64 > 0, PriceOracleError::ZeroPrice);
+    Ok(price_u64)
+}
+
+/// 偶數個來源時取中間兩者的平均
+fn median_price(sources: &[(Pubkey, u64)]) -> Result<u64> {
+    let mut prices: Vec<u64> = sources.iter().map(|(_, price)| *price).collect();
+    prices.sort_unstable();
+
+    let mid = prices.len() / 2;
+    match prices.len() {
+        0 => Err(PriceOracleError::PriceFetchFailed.into()),
+        len if len % 2 == 1 => Ok(prices[mid]),
+        _ => Ok(((prices[mid - 1] as u128 + prices[mid] as u128) / 2) as u64),
+    }
+}
+
+/// 回報偏離聚合價格超過門檻的來源；門檻為 0 表示未設定
+fn flag_deviations(symbol: &str, sources: &[(Pubkey, u64)], reference_price: u64, max_deviation_bps: u64) -> Result<bool> {
+    if max_deviation_bps == 0 || reference_price == 0 {
+        return Ok(false);
+    }
+
+    let mut flagged = false;
+    for (source, price) in sources {
+        let deviation_bps = (*price as u128)
+            .abs_diff(reference_price as u128)
+            .checked_mul(BPS_DENOMINATOR as u128)
+            .ok_or(PriceOracleError::ValueOverflow)?
+            / reference_price as u128;
+
+        if deviation_bps > max_deviation_bps as u128 {
+            flagged = true;
+            emit!(PriceDeviationEvent {
+                symbol: symbol.to_string(),
+                source: *source,
+                price: *price,
+                reference_price,
+                deviation_bps: deviation_bps as u64,
+            });
+        }
     }
+
+    Ok(flagged)
+}
+
+/// 解析 Switchboard feed 並依 guards 檢查其自身的更新 slot、樣本數與信賴區間
+fn load_checked_feed(
+    feed: &AccountInfo<'_>,
+    guards: &FeedGuards,
+    clock: &Clock,
+) -> Result<Box<PullFeedAccountData>> {
+    let feed = read_feed_account(feed)?;
+    require!(feed.result.slot > 0, PriceOracleError::PriceFetchFailed);
+
+    require!(
+        clock.slot.saturating_sub(feed.result.slot) <= guards.max_slot_staleness,
+        PriceOracleError::StaleFeed
+    );
+
+    let fresh_samples = feed
+        .submissions
+        .iter()
+        .take_while(|submission| !submission.is_empty())
+        .filter(|submission| clock.slot.saturating_sub(submission.slot) <= guards.max_slot_staleness)
+        .count();
+    require!(fresh_samples >= guards.min_samples as usize, PriceOracleError::InsufficientSamples);
+
+    require!(feed.result.value > 0, PriceOracleError::ZeroPrice);
+    let max_std_dev = feed
+        .result
+        .value
+        .checked_mul(guards.max_confidence_bps as i128)
+        .ok_or(PriceOracleError::ValueOverflow)?
+        / BPS_DENOMINATOR as i128;
+    require!(feed.result.std_dev <= max_std_dev, PriceOracleError::ConfidenceTooWide);
+
+    Ok(feed)
+}
+
+/// 將 feed 帳戶資料複製到對齊的堆積記憶體再解讀；`PullFeedAccountData::parse` 要求帳戶資料本身符合 i128 對齊
+fn read_feed_account(feed: &AccountInfo<'_>) -> Result<Box<PullFeedAccountData>> {
+    require!(is_trusted_feed_owner(feed.owner), PriceOracleError::UntrustedFeedOwner);
+    let data = feed.try_borrow_data()?;
+    let size = std::mem::size_of::<PullFeedAccountData>();
+    require!(data.len() >= 8 + size, PriceOracleError::PriceFetchFailed);
+    require!(data[..8] == PullFeedAccountData::discriminator(), PriceOracleError::PriceFetchFailed);
+
+    let mut parsed = bytemuck::zeroed_box::<PullFeedAccountData>();
+    bytemuck::bytes_of_mut(parsed.as_mut()).copy_from_slice(&data[8..8 + size]);
+    Ok(parsed)
+}
+
+/// 只接受 Switchboard On-Demand 程式擁有的 feed；mock-oracle 版本另外接受 mock_oracle 建立的模擬 feed
+fn is_trusted_feed_owner(owner: &Pubkey) -> bool {
+    if *owner == ON_DEMAND_MAINNET_PID || *owner == ON_DEMAND_DEVNET_PID {
+        return true;
+    }
+    #[cfg(feature = "mock-oracle")]
+    if *owner == mock_oracle::ID {
+        return true;
+    }
+    false
+}
+
+fn validate_feed_params(max_staleness: i64, decimals: u8) -> Result<()> {
+    require!(max_staleness > 0, PriceOracleError::InvalidStaleness);
+    require!(decimals <= MAX_FEED_DECIMALS, PriceOracleError::InvalidDecimals);
+    Ok(())
+}
+
+/// 將十進位數乘以 scale 後截斷為整數，負值與溢位會明確回傳錯誤
+fn decimal_to_fixed(value: Decimal, scale: u64) -> Result<u64> {
+    require!(!value.is_sign_negative(), PriceOracleError::NegativeValue);
+    value
+        .checked_mul(Decimal::from(scale))
+        .and_then(|scaled| scaled.trunc().to_u64())
+        .ok_or_else(|| PriceOracleError::ValueOverflow.into())
+}
+
+/// 將 Switchboard 的 18 位小數數值截斷為 decimals 位的定點數
+fn scale_feed_value(value: i128, decimals: u8) -> Result<u64> {
+    require!(value >= 0, PriceOracleError::PriceConversionFailed);
+    let scaled = value / 10i128.pow(SWITCHBOARD_DECIMALS - decimals as u32);
+    u64::try_from(scaled).map_err(|_| PriceOracleError::PriceConversionFailed.into())
 }
 
 #[derive(Accounts)]
 pub struct Initialize<'info> {
-    #[account(init, payer = authority, space = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 12 * 8)]
+    #[account(init, payer = authority, space = 8 + 32)]
     pub oracle_account: Account<'info, OracleAccount>,
     #[account(mut)]
     pub authority: Signer<'info>,
-    /// CHECK: This is the Switchboard SOL feed account, which is validated in the instruction logic
-    pub sol_feed: AccountInfo<'info>,
-    /// CHECK: This is the Switchboard Interest Asset feed account
-    pub interest_asset_feed: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(symbol: String)]
+pub struct AddFeed<'info> {
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        init,
+        payer = authority,
+        space = FeedConfig::SPACE,
+        seeds = [b"feed", oracle_account.key().as_ref(), symbol.as_bytes()],
+        bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    #[account(
+        init,
+        payer = authority,
+        space = PriceHistory::SPACE,
+        seeds = [b"history", feed_config.key().as_ref()],
+        bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    /// CHECK: This is the Switchboard feed account being registered
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeed<'info> {
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        seeds = [b"feed", oracle_account.key().as_ref(), feed_config.symbol.as_bytes()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    /// CHECK: This is the Switchboard feed account replacing the registered one
+    pub feed: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFeed<'info> {
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"feed", oracle_account.key().as_ref(), feed_config.symbol.as_bytes()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"history", feed_config.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFallbackSources<'info> {
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        seeds = [b"feed", oracle_account.key().as_ref(), feed_config.symbol.as_bytes()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PushManualPrice<'info> {
+    /// 只有該 Oracle 的 authority 能為其 feed 推送人工價格
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        seeds = [b"feed", oracle_account.key().as_ref(), feed_config.symbol.as_bytes()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct GetPrice<'info> {
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        seeds = [b"feed", oracle_account.key().as_ref(), feed_config.symbol.as_bytes()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    /// CHECK: This is the Switchboard feed account, which must match the registry entry
+    #[account(address = feed_config.feed @ PriceOracleError::FeedMismatch)]
+    pub feed: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"history", feed_config.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+}
+
+#[derive(Accounts)]
+pub struct GetPriceData<'info> {
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        seeds = [b"feed", oracle_account.key().as_ref(), feed_config.symbol.as_bytes()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    /// CHECK: This is the Switchboard feed account, which must match the registry entry
+    #[account(address = feed_config.feed @ PriceOracleError::FeedMismatch)]
+    pub feed: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"history", feed_config.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+}
+
+#[derive(Accounts)]
+pub struct AddLstAsset<'info> {
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        init,
+        payer = authority,
+        space = LstAsset::SPACE,
+        seeds = [b"lst", oracle_account.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub lst_asset: Account<'info, LstAsset>,
+    #[account(
+        init,
+        payer = authority,
+        space = PriceHistory::SPACE,
+        seeds = [b"history", lst_asset.key().as_ref()],
+        bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    /// CHECK: This is the LST mint the registry entry is keyed by
+    pub mint: AccountInfo<'info>,
+    /// CHECK: This is the Switchboard feed reporting the LST price
+    pub price_feed: AccountInfo<'info>,
+    /// CHECK: This is the Switchboard feed reporting the LST APY
+    pub apy_feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLstAsset<'info> {
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        seeds = [b"lst", oracle_account.key().as_ref(), lst_asset.mint.as_ref()],
+        bump = lst_asset.bump
+    )]
+    pub lst_asset: Account<'info, LstAsset>,
+    /// CHECK: This is the Switchboard feed replacing the registered price feed
+    pub price_feed: AccountInfo<'info>,
+    /// CHECK: This is the Switchboard feed replacing the registered APY feed
+    pub apy_feed: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveLstAsset<'info> {
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"lst", oracle_account.key().as_ref(), lst_asset.mint.as_ref()],
+        bump = lst_asset.bump
+    )]
+    pub lst_asset: Account<'info, LstAsset>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"history", lst_asset.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
     #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshLstAsset<'info> {
+    #[account(
+        mut,
+        seeds = [b"lst", lst_asset.oracle.as_ref(), lst_asset.mint.as_ref()],
+        bump = lst_asset.bump
+    )]
+    pub lst_asset: Account<'info, LstAsset>,
+    /// CHECK: This is the Switchboard price feed, which must match the registry entry
+    #[account(address = lst_asset.price_feed @ PriceOracleError::FeedMismatch)]
+    pub price_feed: AccountInfo<'info>,
+    /// CHECK: This is the Switchboard APY feed, which must match the registry entry
+    #[account(address = lst_asset.apy_feed @ PriceOracleError::FeedMismatch)]
+    pub apy_feed: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"history", lst_asset.key().as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+}
+
+#[derive(Accounts)]
+pub struct ManageCircuitBreaker<'info> {
+    #[account(has_one = authority @ PriceOracleError::Unauthorized)]
     pub oracle_account: Account<'info, OracleAccount>,
-    /// CHECK: This is the Switchboard SOL feed account, which is validated in the instruction logic
-    pub sol_feed: AccountInfo<'info>,
-    /// CHECK: This is the Switchboard Interest Asset feed account
-    pub interest_asset_feed: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"history", price_history.source.as_ref()],
+        bump = price_history.bump,
+        constraint = price_history.oracle == oracle_account.key() @ PriceOracleError::Unauthorized
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetTwap<'info> {
+    #[account(
+        seeds = [b"history", price_history.source.as_ref()],
+        bump = price_history.bump
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+}
+
+#[derive(Accounts)]
+pub struct GetLstPriceData<'info> {
+    #[account(
+        seeds = [b"lst", lst_asset.oracle.as_ref(), lst_asset.mint.as_ref()],
+        bump = lst_asset.bump
+    )]
+    pub lst_asset: Account<'info, LstAsset>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PriceData {
+    pub price: u64,
+    pub exponent: i32,
+    pub confidence: u64,
+    pub publish_time: i64,
+    pub source: PriceSource,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PriceSource {
+    Switchboard,
+    Cache,
+}
+
+#[account]
+pub struct FeedConfig {
+    pub oracle: Pubkey,
+    pub symbol: String,
+    pub feed: Pubkey,
+    pub max_staleness: i64,
+    pub decimals: u8,
+    pub guards: FeedGuards,
+    /// 主要 feed 失效時依序使用的備援 Switchboard feed
+    pub fallback_feeds: Vec<Pubkey>,
+    /// authority 推送的手動價格，與 feed 價格使用相同的小數位數
+    pub manual_price: u64,
+    pub manual_price_timestamp: i64,
+    /// 手動價格的有效秒數，0 表示停用
+    pub manual_max_age: i64,
+    /// 來源間偏離的上限（基點），0 表示未設定
+    pub max_deviation_bps: u64,
+    pub deviation_flagged: bool,
+    /// 最近一次聚合後的價格，小數位數與 decimals 相同
+    pub cached_price: u64,
+    pub last_update_timestamp: i64,
+    pub bump: u8,
+}
+
+impl FeedConfig {
+    pub const SPACE: usize = 8 + 32 + 4 + MAX_SYMBOL_LEN + 32 + 8 + 1 + FeedGuards::SPACE
+        + 4 + 32 * MAX_FALLBACK_FEEDS + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 1;
+}
+
+/// 讀取 feed 時的品質門檻，不符合的數據一律拒絕而非沿用
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct FeedGuards {
+    /// feed 結果簽署 slot 與當前 slot 的最大差距
+    pub max_slot_staleness: u64,
+    /// std_dev 相對於價格的上限（基點）
+    pub max_confidence_bps: u64,
+    /// 在 max_slot_staleness 內最少需要的 oracle 提交數
+    pub min_samples: u32,
+}
+
+impl FeedGuards {
+    pub const SPACE: usize = 8 + 8 + 4;
+
+    fn validate(&self) -> Result<()> {
+        require!(self.max_slot_staleness > 0, PriceOracleError::InvalidGuards);
+        require!(
+            self.max_confidence_bps > 0 && self.max_confidence_bps <= BPS_DENOMINATOR,
+            PriceOracleError::InvalidGuards
+        );
+        require!(self.min_samples > 0, PriceOracleError::InvalidGuards);
+        Ok(())
+    }
 }
 
 #[account]
 pub struct OracleAccount {
     pub authority: Pubkey,
-    pub sol_feed: Pubkey,
-    pub interest_asset_feed: Pubkey,
-    pub last_update_timestamp_sol: i64,
-    pub cached_price_sol: u64,
-    pub last_update_timestamp_interest_asset: i64,
-    pub jupsol_price: f64,
-    pub jupsol_apy: f64,
-    pub vsol_price: f64,
-    pub vsol_apy: f64,
-    pub bsol_price: f64,
-    pub bsol_apy: f64,
-    pub msol_price: f64,
-    pub msol_apy: f64,
-    pub hsol_price: f64,
-    pub hsol_apy: f64,
-    pub jitosol_price: f64,
-    pub jitosol_apy: f64,
+}
+
+/// 每個 LST mint 一個帳戶，新增質押代幣只需註冊，不需升級程式
+#[account]
+pub struct LstAsset {
+    pub oracle: Pubkey,
+    pub mint: Pubkey,
+    pub symbol: String,
+    pub price_feed: Pubkey,
+    pub apy_feed: Pubkey,
+    pub max_staleness: i64,
+    pub guards: FeedGuards,
+    /// 價格為 LST_PRICE_DECIMALS 位小數的定點數，APY 以基點表示
+    pub price: u64,
+    pub apy: u64,
+    pub last_update_timestamp: i64,
+    pub bump: u8,
+}
+
+impl LstAsset {
+    pub const SPACE: usize = 8 + 32 + 32 + 4 + MAX_SYMBOL_LEN + 32 + 32 + 8 + FeedGuards::SPACE + 8 + 8 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub price: u64,
+    /// 自第一筆觀測起 price × 經過秒數的累計值
+    pub cumulative_price: u128,
+}
+
+/// 每個資產（FeedConfig 或 LstAsset）一個環形緩衝區，在每次實際讀取 feed 時寫入
+#[account]
+pub struct PriceHistory {
+    pub oracle: Pubkey,
+    pub source: Pubkey,
+    pub symbol: String,
+    /// 緩衝區已滿時下一筆觀測覆寫的位置
+    pub head: u16,
+    pub observations: Vec<Observation>,
+    /// 相鄰兩次更新允許的最大價格變動（基點），0 表示停用熔斷
+    pub max_move_bps: u64,
+    /// 熔斷後連續多少次正常更新即自動恢復，0 表示只能由 authority 確認恢復
+    pub stabilization_updates: u8,
+    /// 熔斷中的資產不得被下游用於存款、鑄造或贖回
+    pub halted: bool,
+    pub stable_updates: u8,
+    pub bump: u8,
+}
+
+impl PriceHistory {
+    pub const SPACE: usize = 8 + 32 + 32 + 4 + MAX_SYMBOL_LEN + 2 + 4 + 32 * MAX_OBSERVATIONS + 8 + 1 + 1 + 1 + 1;
+
+    fn latest(&self) -> Option<&Observation> {
+        if self.observations.len() < MAX_OBSERVATIONS {
+            self.observations.last()
+        } else {
+            let index = (self.head as usize + MAX_OBSERVATIONS - 1) % MAX_OBSERVATIONS;
+            self.observations.get(index)
+        }
+    }
+
+    fn record(&mut self, timestamp: i64, price: u64) -> Result<()> {
+        let (cumulative_price, previous_price) = match self.latest() {
+            Some(last) if timestamp <= last.timestamp => return Ok(()),
+            Some(last) => (cumulative_at(last, timestamp)?, Some(last.price)),
+            None => (0, None),
+        };
+
+        if let Some(previous_price) = previous_price {
+            self.check_circuit_breaker(previous_price, price)?;
+        }
+
+        let observation = Observation {
+            timestamp,
+            price,
+            cumulative_price,
+        };
+
+        if self.observations.len() < MAX_OBSERVATIONS {
+            self.observations.push(observation);
+        } else {
+            self.observations[self.head as usize] = observation;
+            self.head = ((self.head as usize + 1) % MAX_OBSERVATIONS) as u16;
+        }
+
+        Ok(())
+    }
+
+    fn check_circuit_breaker(&mut self, previous_price: u64, price: u64) -> Result<()> {
+        if self.max_move_bps == 0 || previous_price == 0 {
+            return Ok(());
+        }
+
+        let move_bps = (price as u128)
+            .abs_diff(previous_price as u128)
+            .checked_mul(BPS_DENOMINATOR as u128)
+            .ok_or(PriceOracleError::ValueOverflow)?
+            / previous_price as u128;
+
+        if move_bps > self.max_move_bps as u128 {
+            self.stable_updates = 0;
+            if !self.halted {
+                self.halted = true;
+                emit!(CircuitBreakerTrippedEvent {
+                    symbol: self.symbol.clone(),
+                    previous_price,
+                    new_price: price,
+                    move_bps: u64::try_from(move_bps).unwrap_or(u64::MAX),
+                });
+                msg!("Circuit breaker tripped for {}: moved {} bps", self.symbol, move_bps);
+            }
+        } else if self.halted && self.stabilization_updates > 0 {
+            self.stable_updates = self.stable_updates.saturating_add(1);
+            if self.stable_updates >= self.stabilization_updates {
+                self.halted = false;
+                self.stable_updates = 0;
+                emit!(CircuitBreakerResetEvent {
+                    symbol: self.symbol.clone(),
+                    acknowledged: false,
+                });
+                msg!("Circuit breaker reset for {} after price stabilized", self.symbol);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 回傳 [now - window_secs, now] 的時間加權平均價格，歷史不足以涵蓋整個區間時回傳錯誤
+    pub fn twap(&self, now: i64, window_secs: i64) -> Result<u64> {
+        require!(window_secs > 0, PriceOracleError::InvalidTwapWindow);
+        let latest = self.latest().ok_or(PriceOracleError::InsufficientHistory)?;
+        let target = now.checked_sub(window_secs).ok_or(PriceOracleError::ValueOverflow)?;
+
+        let start = self
+            .observations
+            .iter()
+            .filter(|observation| observation.timestamp <= target)
+            .max_by_key(|observation| observation.timestamp)
+            .ok_or(PriceOracleError::InsufficientHistory)?;
+
+        let cumulative_delta = cumulative_at(latest, now)?
+            .checked_sub(cumulative_at(start, target)?)
+            .ok_or(PriceOracleError::ValueOverflow)?;
+
+        u64::try_from(cumulative_delta / window_secs as u128).map_err(|_| PriceOracleError::ValueOverflow.into())
+    }
+}
+
+fn cumulative_at(observation: &Observation, timestamp: i64) -> Result<u128> {
+    let elapsed = u128::try_from(timestamp - observation.timestamp).map_err(|_| PriceOracleError::ValueOverflow)?;
+    (observation.price as u128)
+        .checked_mul(elapsed)
+        .and_then(|delta| observation.cumulative_price.checked_add(delta))
+        .ok_or_else(|| PriceOracleError::ValueOverflow.into())
 }
 
 #[error_code]
@@ -168,4 +1143,118 @@ pub enum PriceOracleError {
     InvalidFeedKey,
     #[msg("Invalid Data Format")]
     InvalidDataFormat,
-}
\ No newline at end of file
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid Symbol")]
+    InvalidSymbol,
+    #[msg("Invalid Staleness Limit")]
+    InvalidStaleness,
+    #[msg("Invalid Decimals")]
+    InvalidDecimals,
+    #[msg("Feed Does Not Match Registry")]
+    FeedMismatch,
+    #[msg("Value Overflow")]
+    ValueOverflow,
+    #[msg("Negative Value")]
+    NegativeValue,
+    #[msg("Invalid Feed Guards")]
+    InvalidGuards,
+    #[msg("Feed Result Is Stale")]
+    StaleFeed,
+    #[msg("Insufficient Oracle Samples")]
+    InsufficientSamples,
+    #[msg("Confidence Interval Too Wide")]
+    ConfidenceTooWide,
+    #[msg("Too Many Fallback Feeds")]
+    TooManyFallbackFeeds,
+    #[msg("Duplicate Feed")]
+    DuplicateFeed,
+    #[msg("Invalid Deviation Threshold")]
+    InvalidDeviationThreshold,
+    #[msg("Invalid TWAP Window")]
+    InvalidTwapWindow,
+    #[msg("Insufficient Price History")]
+    InsufficientHistory,
+    #[msg("Asset Is Not Halted")]
+    NotHalted,
+    #[msg("Feed Account Is Not Owned By A Trusted Oracle Program")]
+    UntrustedFeedOwner,
+    #[msg("Price Is Halted By The Circuit Breaker")]
+    PriceHalted,
+    #[msg("Price Sources Deviate Beyond The Threshold")]
+    PriceDeviationFlagged,
+}
+
+#[event]
+pub struct FeedRegisteredEvent {
+    pub symbol: String,
+    pub feed: Pubkey,
+    pub max_staleness: i64,
+    pub decimals: u8,
+}
+
+#[event]
+pub struct FeedRemovedEvent {
+    pub symbol: String,
+    pub feed: Pubkey,
+}
+
+#[event]
+pub struct LstAssetRegisteredEvent {
+    pub mint: Pubkey,
+    pub symbol: String,
+    pub price_feed: Pubkey,
+    pub apy_feed: Pubkey,
+    pub max_staleness: i64,
+}
+
+#[event]
+pub struct LstAssetRemovedEvent {
+    pub mint: Pubkey,
+    pub symbol: String,
+}
+
+#[event]
+pub struct FallbackSourcesUpdatedEvent {
+    pub symbol: String,
+    pub fallback_feeds: Vec<Pubkey>,
+    pub manual_max_age: i64,
+    pub max_deviation_bps: u64,
+}
+
+#[event]
+pub struct ManualPricePushedEvent {
+    pub symbol: String,
+    pub price: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FallbackUsedEvent {
+    pub symbol: String,
+    pub price: u64,
+    pub sources_used: u8,
+}
+
+#[event]
+pub struct PriceDeviationEvent {
+    pub symbol: String,
+    pub source: Pubkey,
+    pub price: u64,
+    pub reference_price: u64,
+    pub deviation_bps: u64,
+}
+
+#[event]
+pub struct CircuitBreakerTrippedEvent {
+    pub symbol: String,
+    pub previous_price: u64,
+    pub new_price: u64,
+    pub move_bps: u64,
+}
+
+#[event]
+pub struct CircuitBreakerResetEvent {
+    pub symbol: String,
+    pub acknowledged: bool,
+}
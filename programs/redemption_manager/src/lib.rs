@@ -1,6 +1,13 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 use anchor_spl::token::{self, Token, TokenAccount, Burn};
 use solana_program::native_token::LAMPORTS_PER_SOL;
+use access_control::AccessControl;
+use asset_manager::program::AssetManager;
+use asset_manager::ProgramState;
+use lock_manager::LockRecord;
+use price_oracle::program::PriceOracle;
+use price_oracle::{FeedConfig, OracleAccount, PriceData, PriceHistory};
 
 declare_id!("8uUo5wwK7LykM53dX1wGM4iS8HCZFVZiD6PeQ1xUqKLA");
 
@@ -8,23 +15,71 @@ declare_id!("8uUo5wwK7LykM53dX1wGM4iS8HCZFVZiD6PeQ1xUqKLA");
 pub mod redemption_manager {
     use super::*;
 
-    pub fn initialize_system_state(ctx: Context<InitializeSystemState>) -> Result<()> {
+    pub fn initialize_system_state(ctx: Context<InitializeSystemState>, oracle: Pubkey) -> Result<()> {
         ctx.accounts.system_state.is_paused = false;
+        ctx.accounts.system_state.authority = ctx.accounts.authority.key();
+        ctx.accounts.system_state.oracle = oracle;
         Ok(())
     }
 
     pub fn pause_system(ctx: Context<PauseSystem>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.system_state.authority,
+            RedemptionError::Unauthorized
+        );
         ctx.accounts.system_state.is_paused = true;
         Ok(())
     }
 
     pub fn unpause_system(ctx: Context<PauseSystem>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.system_state.authority,
+            RedemptionError::Unauthorized
+        );
         ctx.accounts.system_state.is_paused = false;
         Ok(())
     }
 
-    pub fn initiate_redeem(ctx: Context<InitiateRedeem>, amount: u64) -> Result<()> {
+    pub fn transfer_authority(ctx: Context<TransferAuthority>, new_authority: Pubkey) -> Result<()> {
+        require!(new_authority != Pubkey::default(), RedemptionError::InvalidAuthority);
+
+        let system_state = &mut ctx.accounts.system_state;
+        let old_authority = system_state.authority;
+        system_state.authority = new_authority;
+
+        emit!(AuthorityTransferredEvent {
+            old_authority,
+            new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// 注資贖回 PDA：首次呼叫時建立由本程式擁有的帳戶，之後贖回才能直接從中扣款出金
+    pub fn fund_redemption_pool(ctx: Context<FundRedemptionPool>, amount: u64) -> Result<()> {
+        require!(amount > 0, RedemptionError::InvalidAmount);
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.funder.to_account_info(),
+                to: ctx.accounts.redemption_manager.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_ctx, amount)?;
+
+        emit!(RedemptionPoolFundedEvent {
+            funder: ctx.accounts.funder.key(),
+            amount,
+            balance: ctx.accounts.redemption_manager.lamports(),
+        });
+
+        Ok(())
+    }
+
+    pub fn initiate_redeem(ctx: Context<InitiateRedeem>, lock_id: u64, amount: u64) -> Result<()> {
         require!(!ctx.accounts.system_state.is_paused, RedemptionError::SystemPaused);
+        require!(!ctx.accounts.access_control.is_paused, RedemptionError::SystemPaused);
 
         let current_time = Clock::get()?.unix_timestamp;
         let lock_end_time = ctx.accounts.lock_record.start_time + (ctx.accounts.lock_record.lock_period as i64 * 86400);
@@ -33,6 +88,15 @@ pub mod redemption_manager {
         let redemption_end_time = lock_end_time + (14 * 86400);
         require!(current_time <= redemption_end_time, RedemptionError::RedemptionPeriodEnded);
 
+        // 同一部位累計的贖回數量不可超過其仍鎖定的餘額
+        let lock_redemption = &mut ctx.accounts.lock_redemption;
+        let redeemed = lock_redemption.redeemed
+            .checked_add(amount)
+            .ok_or(RedemptionError::CalculationError)?;
+        require!(redeemed <= ctx.accounts.lock_record.amount, RedemptionError::ExceedsLockedAmount);
+        lock_redemption.user = ctx.accounts.user.key();
+        lock_redemption.lock_id = lock_id;
+        lock_redemption.redeemed = redeemed;
         require!(ctx.accounts.user_token_account.amount >= amount, RedemptionError::InsufficientBalance);
 
         let cpi_accounts = token::Transfer {
@@ -46,12 +110,14 @@ pub mod redemption_manager {
 
         let redemption_request = &mut ctx.accounts.redemption_request;
         redemption_request.user = ctx.accounts.user.key();
+        redemption_request.lock_id = lock_id;
         redemption_request.amount = amount;
         redemption_request.request_time = current_time;
         redemption_request.is_processed = false;
 
         emit!(RedemptionInitiatedEvent {
             user: ctx.accounts.user.key(),
+            lock_id,
             amount,
             request_time: current_time,
         });
@@ -59,13 +125,27 @@ pub mod redemption_manager {
         Ok(())
     }
 
-    pub fn execute_redeem(ctx: Context<ExecuteRedeem>) -> Result<()> {
+    pub fn execute_redeem<'info>(ctx: Context<'_, '_, '_, 'info, ExecuteRedeem<'info>>, lock_id: u64) -> Result<()> {
         require!(!ctx.accounts.system_state.is_paused, RedemptionError::SystemPaused);
+        require!(!ctx.accounts.access_control.is_paused, RedemptionError::SystemPaused);
+        require!(!ctx.accounts.sol_price_history.halted, RedemptionError::OracleHalted);
 
         let redemption_request = &mut ctx.accounts.redemption_request;
         require!(!redemption_request.is_processed, RedemptionError::AlreadyProcessed);
 
-        let sol_amount = redemption_request.amount / LAMPORTS_PER_SOL;
+        // 以 Oracle 彙整後的 SOL 價格將 xxUSD 換算為 lamports，備援 feed 依註冊順序放在 remaining_accounts
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.price_oracle_program.to_account_info(),
+            price_oracle::cpi::accounts::GetPriceData {
+                oracle_account: ctx.accounts.oracle_account.to_account_info(),
+                feed_config: ctx.accounts.sol_feed_config.to_account_info(),
+                feed: ctx.accounts.sol_feed.to_account_info(),
+                price_history: ctx.accounts.sol_price_history.to_account_info(),
+            },
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        let price_data = price_oracle::cpi::get_price_data(cpi_ctx, "SOL".to_string())?.get();
+        let sol_amount = xxusd_to_lamports(redemption_request.amount, ctx.accounts.xxusd_mint.decimals, &price_data)?;
 
         let cpi_accounts = Burn {
             mint: ctx.accounts.xxusd_mint.to_account_info(),
@@ -81,13 +161,44 @@ pub mod redemption_manager {
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
         token::burn(cpi_ctx, redemption_request.amount)?;
 
-        **ctx.accounts.redemption_manager.to_account_info().try_borrow_mut_lamports()? -= sol_amount;
-        **ctx.accounts.user.to_account_info().try_borrow_mut_lamports()? += sol_amount;
+        // 銷毀的 xxUSD 同步從 asset_manager 的流通量與用戶鑄造紀錄中扣除
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.asset_manager_program.to_account_info(),
+            asset_manager::cpi::accounts::RecordRedemptionBurn {
+                state: ctx.accounts.asset_manager_state.to_account_info(),
+                xxusd_mint: ctx.accounts.xxusd_mint.to_account_info(),
+                mint_authority: ctx.accounts.xxusd_mint_authority.to_account_info(),
+                // 用戶從未透過 asset_manager 鑄造時沒有鑄造紀錄
+                user_mint: (ctx.accounts.user_mint.owner == &asset_manager::ID)
+                    .then(|| ctx.accounts.user_mint.to_account_info()),
+                user: ctx.accounts.user.to_account_info(),
+                redemption_manager: ctx.accounts.redemption_manager.to_account_info(),
+            },
+            signer,
+        );
+        asset_manager::cpi::record_redemption_burn(cpi_ctx, redemption_request.amount)?;
+
+        // 出金後贖回 PDA 仍須保有免租金的最低餘額
+        let pool = ctx.accounts.redemption_manager.to_account_info();
+        let remaining = pool.lamports()
+            .checked_sub(sol_amount)
+            .ok_or(RedemptionError::InsufficientLiquidity)?;
+        require!(
+            remaining >= Rent::get()?.minimum_balance(pool.data_len()),
+            RedemptionError::InsufficientLiquidity
+        );
+        **pool.try_borrow_mut_lamports()? = remaining;
+        let user = ctx.accounts.user.to_account_info();
+        let user_lamports = user.lamports()
+            .checked_add(sol_amount)
+            .ok_or(RedemptionError::CalculationError)?;
+        **user.try_borrow_mut_lamports()? = user_lamports;
 
         redemption_request.is_processed = true;
 
         emit!(RedemptionExecutedEvent {
             user: ctx.accounts.user.key(),
+            lock_id,
             amount: redemption_request.amount,
             sol_amount,
         });
@@ -95,7 +206,17 @@ pub mod redemption_manager {
         Ok(())
     }
 
-    pub fn check_redeem_eligibility(ctx: Context<CheckRedeemEligibility>) -> Result<bool> {
+    /// 關閉已處理的贖回請求，租金退回用戶，同一部位之後可在剩餘額度內再次發起贖回
+    pub fn close_redemption_request(ctx: Context<CloseRedemptionRequest>, lock_id: u64) -> Result<()> {
+        emit!(RedemptionRequestClosedEvent {
+            user: ctx.accounts.user.key(),
+            lock_id,
+        });
+
+        Ok(())
+    }
+
+    pub fn check_redeem_eligibility(ctx: Context<CheckRedeemEligibility>, _lock_id: u64) -> Result<bool> {
         let lock_record = &ctx.accounts.lock_record;
         let current_time = Clock::get()?.unix_timestamp;
         let lock_end_time = lock_record.start_time + (lock_record.lock_period as i64 * 86400);
@@ -109,6 +230,10 @@ pub mod redemption_manager {
             return Ok(false);
         }
 
+        if lock_record.amount == 0 {
+            return Ok(false);
+        }
+
         let user_balance = ctx.accounts.user_token_account.amount;
         if user_balance == 0 {
             return Ok(false);
@@ -118,9 +243,40 @@ pub mod redemption_manager {
     }
 }
 
+/// lamports = xxUSD 數量 × 10^9 / (價格 × 10^xxUSD 小數位數)，價格依 PriceData 的 exponent 還原
+fn xxusd_to_lamports(amount: u64, xxusd_decimals: u8, price_data: &PriceData) -> Result<u64> {
+    require!(price_data.price > 0 && price_data.exponent <= 0, RedemptionError::InvalidOracle);
+
+    let numerator_pow = LAMPORTS_PER_SOL.ilog10() + price_data.exponent.unsigned_abs();
+    let denominator_pow = xxusd_decimals as u32;
+    let (numerator, denominator) = if numerator_pow >= denominator_pow {
+        (
+            (amount as u128).checked_mul(10u128.pow(numerator_pow - denominator_pow)),
+            Some(price_data.price as u128),
+        )
+    } else {
+        (
+            Some(amount as u128),
+            (price_data.price as u128).checked_mul(10u128.pow(denominator_pow - numerator_pow)),
+        )
+    };
+
+    let lamports = numerator
+        .zip(denominator)
+        .map(|(numerator, denominator)| numerator / denominator)
+        .ok_or(RedemptionError::CalculationError)?;
+    u64::try_from(lamports).map_err(|_| RedemptionError::CalculationError.into())
+}
+
 #[derive(Accounts)]
 pub struct InitializeSystemState<'info> {
-    #[account(init, payer = authority, space = 8 + 1)]
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 32 + 32,
+        seeds = [b"system_state"],
+        bump
+    )]
     pub system_state: Account<'info, SystemState>,
     #[account(mut)]
     pub authority: Signer<'info>,
@@ -129,57 +285,145 @@ pub struct InitializeSystemState<'info> {
 
 #[derive(Accounts)]
 pub struct PauseSystem<'info> {
-    #[account(mut)]
+    #[account(mut, seeds = [b"system_state"], bump)]
+    pub system_state: Account<'info, SystemState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"system_state"],
+        bump,
+        constraint = system_state.authority == authority.key() @ RedemptionError::Unauthorized,
+    )]
     pub system_state: Account<'info, SystemState>,
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
+pub struct FundRedemptionPool<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    /// CHECK: 贖回出金用的 SOL 池，不存放資料，只要求由本程式擁有
+    #[account(
+        init_if_needed,
+        payer = funder,
+        space = 0,
+        owner = crate::ID,
+        seeds = [b"redemption_manager"],
+        bump
+    )]
+    pub redemption_manager: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: u64)]
 pub struct InitiateRedeem<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     #[account(
         mut,
+        token::mint = xxusd_mint,
         constraint = user_token_account.owner == user.key() @ RedemptionError::InvalidOwner
     )]
     pub user_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub redemption_vault: Account<'info, TokenAccount>,
     #[account(
         mut,
-        seeds = [b"lock_record", user.key().as_ref()],
+        associated_token::mint = xxusd_mint,
+        associated_token::authority = redemption_manager,
+    )]
+    pub redemption_vault: Account<'info, TokenAccount>,
+    #[account(seeds = [b"state"], bump, seeds::program = asset_manager::ID)]
+    pub asset_manager_state: Account<'info, ProgramState>,
+    #[account(address = asset_manager_state.xxusd_mint @ RedemptionError::InvalidMint)]
+    pub xxusd_mint: Account<'info, token::Mint>,
+    /// CHECK: This is the PDA for the redemption manager, only used to derive the vault
+    #[account(seeds = [b"redemption_manager"], bump)]
+    pub redemption_manager: AccountInfo<'info>,
+    #[account(
+        seeds = [b"lock_record", user.key().as_ref(), &lock_id.to_le_bytes()],
         bump,
+        seeds::program = lock_manager::ID,
         constraint = lock_record.owner == user.key() @ RedemptionError::InvalidOwner,
+        constraint = lock_record.mint == xxusd_mint.key() @ RedemptionError::InvalidMint,
     )]
     pub lock_record: Account<'info, LockRecord>,
     #[account(
         init,
         payer = user,
-        space = 8 + 32 + 8 + 8 + 1,
-        seeds = [b"redemption_request", user.key().as_ref()],
+        space = 8 + 32 + 8 + 8 + 8 + 1,
+        seeds = [b"redemption_request", user.key().as_ref(), &lock_id.to_le_bytes()],
         bump
     )]
     pub redemption_request: Account<'info, RedemptionRequest>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 8 + 8,
+        seeds = [b"lock_redemption", user.key().as_ref(), &lock_id.to_le_bytes()],
+        bump
+    )]
+    pub lock_redemption: Account<'info, LockRedemption>,
+    #[account(seeds = [b"system_state"], bump)]
     pub system_state: Account<'info, SystemState>,
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(lock_id: u64)]
 pub struct ExecuteRedeem<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        associated_token::mint = xxusd_mint,
+        associated_token::authority = redemption_manager,
+    )]
     pub redemption_vault: Account<'info, TokenAccount>,
     #[account(
         mut,
-        seeds = [b"redemption_request", user.key().as_ref()],
+        seeds = [b"redemption_request", user.key().as_ref(), &lock_id.to_le_bytes()],
         bump,
         constraint = redemption_request.user == user.key() @ RedemptionError::InvalidOwner,
     )]
     pub redemption_request: Account<'info, RedemptionRequest>,
+    #[account(seeds = [b"system_state"], bump)]
     pub system_state: Account<'info, SystemState>,
-    #[account(mut)]
+    #[account(
+        seeds = [b"access_control"],
+        bump,
+        seeds::program = access_control::ID,
+    )]
+    pub access_control: Account<'info, AccessControl>,
+    #[account(
+        mut,
+        constraint = sol_price_history.oracle == system_state.oracle @ RedemptionError::InvalidOracle,
+        constraint = sol_price_history.symbol == "SOL" @ RedemptionError::InvalidOracle,
+    )]
+    pub sol_price_history: Account<'info, PriceHistory>,
+    #[account(address = system_state.oracle @ RedemptionError::InvalidOracle)]
+    pub oracle_account: Account<'info, OracleAccount>,
+    #[account(
+        mut,
+        seeds = [b"feed", oracle_account.key().as_ref(), b"SOL"],
+        bump = sol_feed_config.bump,
+        seeds::program = price_oracle::ID,
+    )]
+    pub sol_feed_config: Account<'info, FeedConfig>,
+    /// CHECK: Verified by price_oracle against sol_feed_config
+    pub sol_feed: AccountInfo<'info>,
+    pub price_oracle_program: Program<'info, PriceOracle>,
+    #[account(mut, address = asset_manager_state.xxusd_mint @ RedemptionError::InvalidMint)]
     pub xxusd_mint: Account<'info, token::Mint>,
     /// CHECK: This is the PDA for the redemption manager
     #[account(
@@ -188,16 +432,51 @@ pub struct ExecuteRedeem<'info> {
         bump
     )]
     pub redemption_manager: AccountInfo<'info>,
+    #[account(mut, seeds = [b"state"], bump, seeds::program = asset_manager::ID)]
+    pub asset_manager_state: Account<'info, ProgramState>,
+    /// CHECK: AssetManager 的 xxUSD 鑄幣權限 PDA
+    #[account(
+        seeds = [asset_manager_state.key().as_ref()],
+        bump = asset_manager_state.nonce,
+        seeds::program = asset_manager::ID,
+    )]
+    pub xxusd_mint_authority: AccountInfo<'info>,
+    /// CHECK: 用戶在 asset_manager 的鑄造紀錄，可能尚未建立；存在時由 asset_manager 驗證
+    #[account(
+        mut,
+        seeds = [b"user_mint", user.key().as_ref()],
+        bump,
+        seeds::program = asset_manager::ID,
+    )]
+    pub user_mint: AccountInfo<'info>,
+    pub asset_manager_program: Program<'info, AssetManager>,
     pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct CheckRedeemEligibility<'info> {
+#[instruction(lock_id: u64)]
+pub struct CloseRedemptionRequest<'info> {
+    #[account(mut)]
     pub user: Signer<'info>,
     #[account(
         mut,
-        seeds = [b"lock_record", user.key().as_ref()],
+        close = user,
+        seeds = [b"redemption_request", user.key().as_ref(), &lock_id.to_le_bytes()],
         bump,
+        constraint = redemption_request.user == user.key() @ RedemptionError::InvalidOwner,
+        constraint = redemption_request.is_processed @ RedemptionError::RedemptionNotProcessed,
+    )]
+    pub redemption_request: Account<'info, RedemptionRequest>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: u64)]
+pub struct CheckRedeemEligibility<'info> {
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"lock_record", user.key().as_ref(), &lock_id.to_le_bytes()],
+        bump,
+        seeds::program = lock_manager::ID,
         constraint = lock_record.owner == user.key() @ RedemptionError::InvalidOwner,
     )]
     pub lock_record: Account<'info, LockRecord>,
@@ -206,28 +485,32 @@ pub struct CheckRedeemEligibility<'info> {
         constraint = user_token_account.owner == user.key() @ RedemptionError::InvalidOwner
     )]
     pub user_token_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"system_state"], bump)]
     pub system_state: Account<'info, SystemState>,
 }
 
 #[account]
 pub struct RedemptionRequest {
     pub user: Pubkey,
+    pub lock_id: u64,
     pub amount: u64,
     pub request_time: i64,
     pub is_processed: bool,
 }
 
+/// 記錄單一鎖倉部位累計發起的贖回數量，贖回請求關閉後仍保留
 #[account]
-pub struct SystemState {
-    pub is_paused: bool,
+pub struct LockRedemption {
+    pub user: Pubkey,
+    pub lock_id: u64,
+    pub redeemed: u64,
 }
 
 #[account]
-pub struct LockRecord {
-    pub owner: Pubkey,
-    pub amount: u64,
-    pub lock_period: u64,
-    pub start_time: i64,
+pub struct SystemState {
+    pub is_paused: bool,
+    pub authority: Pubkey,
+    pub oracle: Pubkey,
 }
 
 #[error_code]
@@ -246,11 +529,32 @@ pub enum RedemptionError {
     AlreadyProcessed,
     #[msg("User is not eligible for redemption")]
     NotEligibleForRedemption,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid authority")]
+    InvalidAuthority,
+    #[msg("Invalid oracle account")]
+    InvalidOracle,
+    #[msg("Oracle price is halted by the circuit breaker")]
+    OracleHalted,
+    #[msg("Redemption request has not been processed")]
+    RedemptionNotProcessed,
+    #[msg("Redemption amount exceeds the remaining locked balance")]
+    ExceedsLockedAmount,
+    #[msg("Calculation error")]
+    CalculationError,
+    #[msg("Invalid xxUSD mint")]
+    InvalidMint,
+    #[msg("Insufficient SOL liquidity for redemption")]
+    InsufficientLiquidity,
+    #[msg("Invalid amount")]
+    InvalidAmount,
 }
 
 #[event]
 pub struct RedemptionInitiatedEvent {
     pub user: Pubkey,
+    pub lock_id: u64,
     pub amount: u64,
     pub request_time: i64,
 }
@@ -258,6 +562,26 @@ pub struct RedemptionInitiatedEvent {
 #[event]
 pub struct RedemptionExecutedEvent {
     pub user: Pubkey,
+    pub lock_id: u64,
     pub amount: u64,
     pub sol_amount: u64,
+}
+
+#[event]
+pub struct RedemptionRequestClosedEvent {
+    pub user: Pubkey,
+    pub lock_id: u64,
+}
+
+#[event]
+pub struct RedemptionPoolFundedEvent {
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, Mint, TokenAccount};
+use anchor_spl::token::spl_token::instruction::AuthorityType;
 use anchor_spl::associated_token::AssociatedToken;
+use solana_program::program_option::COption;
 
 declare_id!("HPKJ3WHcaKkagLdZ9iNk1s2knDX19vT26jocrzLKNh3i");
 
@@ -34,6 +36,27 @@ pub mod xxusd_token {
         Ok(())
     }
 
+    /// 將鑄幣權交給新的權限方（例如 asset_manager 的 PDA）
+    pub fn set_mint_authority(ctx: Context<SetMintAuthority>, new_authority: Pubkey) -> Result<()> {
+        token::set_authority(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::SetAuthority {
+                    account_or_mint: ctx.accounts.mint.to_account_info(),
+                    current_authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            AuthorityType::MintTokens,
+            Some(new_authority),
+        )?;
+        emit!(MintAuthorityChanged {
+            mint: ctx.accounts.mint.key(),
+            old_authority: ctx.accounts.authority.key(),
+            new_authority,
+        });
+        Ok(())
+    }
+
     pub fn mint(ctx: Context<MintTo>, amount: u64) -> Result<()> {
         require!(amount > 0, XXUSDError::InvalidAmount);
         token::mint_to(
@@ -102,15 +125,10 @@ pub mod xxusd_token {
 }
 
 #[derive(Accounts)]
-#[instruction(decimals: u8)]
 pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        mint::decimals = decimals,
-        mint::authority = authority.key(),
-    )]
-    pub mint: Account<'info, Mint>,
+    /// CHECK: 只建立帳戶，由處理函式以指定的 decimals 與 freeze authority 初始化 mint
+    #[account(init, payer = authority, space = Mint::LEN, owner = token::ID)]
+    pub mint: UncheckedAccount<'info>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -118,6 +136,17 @@ pub struct Initialize<'info> {
     pub rent: Sysvar<'info, Rent>,
 }
 
+#[derive(Accounts)]
+pub struct SetMintAuthority<'info> {
+    #[account(
+        mut,
+        constraint = mint.mint_authority == COption::Some(authority.key()) @ XXUSDError::Unauthorized
+    )]
+    pub mint: Account<'info, Mint>,
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
 #[derive(Accounts)]
 pub struct MintTo<'info> {
     #[account(mut)]
@@ -177,6 +206,8 @@ pub enum XXUSDError {
     InvalidAmount,
     #[msg("Insufficient funds for the operation.")]
     InsufficientFunds,
+    #[msg("Signer is not the mint authority.")]
+    Unauthorized,
 }
 
 #[event]
@@ -186,6 +217,13 @@ pub struct MintInitialized {
     pub authority: Pubkey,
 }
 
+#[event]
+pub struct MintAuthorityChanged {
+    pub mint: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
 #[event]
 pub struct TokensMinted {
     pub mint: Pubkey,
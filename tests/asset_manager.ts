@@ -2,11 +2,13 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { AssetManager } from "../target/types/asset_manager";
 import { PriceOracle } from "../target/types/price_oracle";
+import { AccessControl } from "../target/types/access_control";
+import { LockManager } from "../target/types/lock_manager";
 import {
   TOKEN_PROGRAM_ID,
   getOrCreateAssociatedTokenAccount,
   createAssociatedTokenAccountInstruction,
-  createInitializeAccountInstruction,
+  getAssociatedTokenAddressSync,
 } from "@solana/spl-token";
 import { expect } from "chai";
 import {
@@ -24,6 +26,8 @@ describe("asset_manager", () => {
 
   const program = anchor.workspace.AssetManager as Program<AssetManager>;
   const priceOracleProgram = anchor.workspace.PriceOracle as Program<PriceOracle>;
+  const accessControlProgram = anchor.workspace.AccessControl as Program<AccessControl>;
+  const lockManagerProgram = anchor.workspace.LockManager as Program<LockManager>;
   const connection = provider.connection;
 
   const user = provider.wallet.publicKey;
@@ -35,13 +39,82 @@ describe("asset_manager", () => {
   let userXxusdAccount: PublicKey;
   let vaultAssetAccount: PublicKey;
   let xxusdVaultAccount: PublicKey;
+  let mintAuthority: PublicKey;
+  let lockVaultAccount: PublicKey;
   let userDepositPda: PublicKey;
   let programState: PublicKey;
   let oracleAccount: Keypair;
+  let accessControlPDA: PublicKey;
 
   const mockSolFeed = new PublicKey("GvDMxPzN1sCj7L26YDK2HnMRXEQmQ2aemov8YBtPS7vR");
   const mockInterestAssetFeed = new PublicKey("4NiWaTuje7SVe9DN1vfnX7m1qBC7DnUxwRxbdgEDUGX1");
 
+  // 讀取 feed 時的品質門檻：slot 新鮮度、信賴區間（基點）與最少樣本數
+  const feedGuards = {
+    maxSlotStaleness: new anchor.BN(150),
+    maxConfidenceBps: new anchor.BN(200),
+    minSamples: 1,
+  };
+
+  const feedConfigPda = (symbol: string): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("feed"), oracleAccount.publicKey.toBuffer(), Buffer.from(symbol)],
+      priceOracleProgram.programId
+    )[0];
+
+  const lstAssetPda = (mint: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lst"), oracleAccount.publicKey.toBuffer(), mint.toBuffer()],
+      priceOracleProgram.programId
+    )[0];
+
+  const priceHistoryPda = (source: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("history"), source.toBuffer()],
+      priceOracleProgram.programId
+    )[0];
+
+  const collateralPda = (mint: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral"), mint.toBuffer()],
+      program.programId
+    )[0];
+
+  const vaultPda = (mint: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), mint.toBuffer()],
+      program.programId
+    )[0];
+
+  const userMintPda = (owner: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("user_mint"), owner.toBuffer()],
+      program.programId
+    )[0];
+
+  const [lockManagerPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("lock_manager")],
+    lockManagerProgram.programId
+  );
+
+  const lockIndexPda = (owner: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lock_index"), owner.toBuffer()],
+      lockManagerProgram.programId
+    )[0];
+
+  const lockRecordPda = (owner: PublicKey, lockId: BN): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lock_record"), owner.toBuffer(), lockId.toArrayLike(Buffer, "le", 8)],
+      lockManagerProgram.programId
+    )[0];
+
+  // 鑄造會開立索引中的下一個部位
+  async function nextLockId(owner: PublicKey): Promise<BN> {
+    const index = await lockManagerProgram.account.userLockIndex.fetchNullable(lockIndexPda(owner));
+    return index ? index.nextId : new BN(0);
+  }
+
   function uiToNative(amount: number, decimals: number): BN {
     return new BN(Math.floor(amount * Math.pow(10, decimals)));
   }
@@ -103,58 +176,25 @@ describe("asset_manager", () => {
     );
     userXxusdAccount = userXxusdAccountInfo.address;
 
-    // Derive PDA for Vault asset account
-    const [vaultAssetPDA, vaultAssetBump] = await PublicKey.findProgramAddress(
-      [Buffer.from("vault"), jupsolMint.toBuffer()],
-      program.programId
-    );
-    vaultAssetAccount = vaultAssetPDA;
-
-    // Create Vault asset account as PDA
-    const createVaultAccountIx = SystemProgram.createAccountWithSeed({
-      fromPubkey: user,
-      newAccountPubkey: vaultAssetAccount,
-      basePubkey: user,
-      seed: "vault",
-      space: 165, // TokenAccount size (8 + 32 + 32 + 64 + 0)
-      lamports: await connection.getMinimumBalanceForRentExemption(165),
-      programId: TOKEN_PROGRAM_ID,
-    });
-
-    const initializeVaultAccountIx = createInitializeAccountInstruction(
-      vaultAssetAccount,
-      jupsolMint,
-      program.programId,
+    // 鑄造時商品價格部分鎖入 lock_manager 的 xxUSD 金庫
+    const lockVaultAccountInfo = await getOrCreateAssociatedTokenAccount(
+      connection,
+      provider.wallet as any,
+      usdcMint,
+      lockManagerPda,
+      true
     );
+    lockVaultAccount = lockVaultAccountInfo.address;
 
-    await createAndSendV0Tx([createVaultAccountIx, initializeVaultAccountIx]);
+    // jupSOL 金庫由 addCollateral 建立
+    vaultAssetAccount = vaultPda(jupsolMint);
 
-    // Derive PDA for xxusdVaultAccount
-    const [xxusdVaultPDA, xxusdVaultBump] = await PublicKey.findProgramAddress(
-      [Buffer.from("vault"), usdcMint.toBuffer()],
-      program.programId
-    );
-    xxusdVaultAccount = xxusdVaultPDA;
-
-    // Create USDC Vault account as PDA
-    const createXxusdVaultAccountIx = SystemProgram.createAccountWithSeed({
-      fromPubkey: user,
-      newAccountPubkey: xxusdVaultAccount,
-      basePubkey: user,
-      seed: "vault",
-      space: 165, // TokenAccount size (8 + 32 + 32 + 64 + 0)
-      lamports: await connection.getMinimumBalanceForRentExemption(165),
-      programId: TOKEN_PROGRAM_ID,
-    });
-
-    const initializeXxusdVaultAccountIx = createInitializeAccountInstruction(
-      xxusdVaultAccount,
-      usdcMint,
-      program.programId,
+    // Derive AccessControl PDA (initialized by the access_control tests)
+    [accessControlPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("access_control")],
+      accessControlProgram.programId
     );
 
-    await createAndSendV0Tx([createXxusdVaultAccountIx, initializeXxusdVaultAccountIx]);
-
     // Initialize Program State PDA
     const [statePda] = PublicKey.findProgramAddressSync(
       [Buffer.from("state")],
@@ -162,6 +202,13 @@ describe("asset_manager", () => {
     );
     programState = statePda;
 
+    // xxUSD 的鑄幣與金庫權限 PDA，金庫為其 ATA，由 initialize 建立
+    [mintAuthority] = PublicKey.findProgramAddressSync(
+      [programState.toBuffer()],
+      program.programId
+    );
+    xxusdVaultAccount = getAssociatedTokenAddressSync(usdcMint, mintAuthority, true);
+
     // Initialize PriceOracle
     oracleAccount = Keypair.generate();
     const initializeOracleInstruction = await priceOracleProgram.methods
@@ -169,24 +216,103 @@ describe("asset_manager", () => {
       .accounts({
         oracleAccount: oracleAccount.publicKey,
         authority: provider.wallet.publicKey,
-        solFeed: mockSolFeed,
-        interestAssetFeed: mockInterestAssetFeed,
         systemProgram: SystemProgram.programId,
       } as any)
       .instruction();
 
     await createAndSendV0Tx([initializeOracleInstruction], [oracleAccount]);
 
+    // 註冊 SOL 數據源與 jupSOL 資產
+    const addSolFeedInstruction = await priceOracleProgram.methods
+      .addFeed("SOL", new anchor.BN(60), 0, feedGuards)
+      .accounts({
+        oracleAccount: oracleAccount.publicKey,
+        feedConfig: feedConfigPda("SOL"),
+        priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+        feed: mockSolFeed,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .instruction();
+
+    const addLstAssetInstruction = await priceOracleProgram.methods
+      .addLstAsset("jupSOL", new anchor.BN(60), feedGuards)
+      .accounts({
+        oracleAccount: oracleAccount.publicKey,
+        lstAsset: lstAssetPda(jupsolMint),
+        priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        mint: jupsolMint,
+        priceFeed: mockInterestAssetFeed,
+        apyFeed: mockInterestAssetFeed,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([addSolFeedInstruction, addLstAssetInstruction]);
+
     // Initialize Program State
     const initializeInstruction = await program.methods
-      .initialize(jupsolMint)
+      .initialize(oracleAccount.publicKey)
+      .accounts({
+        state: programState,
+        xxusdMint: usdcMint,
+        mintAuthority: mintAuthority,
+        xxusdVault: xxusdVaultAccount,
+        accessControl: accessControlPDA,
+        authority: user,
+      } as any)
+      .instruction();
+
+    // 將 xxUSD 鑄幣權交給 PDA
+    const acceptMintAuthorityInstruction = await program.methods
+      .acceptXxusdMintAuthority()
       .accounts({
         state: programState,
+        xxusdMint: usdcMint,
+        mintAuthority: mintAuthority,
         authority: user,
       } as any)
       .instruction();
 
-    await createAndSendV0Tx([initializeInstruction]);
+    await createAndSendV0Tx([initializeInstruction, acceptMintAuthorityInstruction]);
+
+    const state = await program.account.programState.fetch(programState);
+    expect(state.xxusdMint.toString()).to.equal(usdcMint.toString());
+
+    // 註冊 jupSOL 為抵押品：80% LTV，存款上限 1,000 jupSOL
+    const addCollateralInstruction = await program.methods
+      .addCollateral(8000, uiToNative(1000, 9))
+      .accounts({
+        state: programState,
+        assetMint: jupsolMint,
+        collateral: collateralPda(jupsolMint),
+        vaultAssetAccount: vaultAssetAccount,
+        lstAsset: lstAssetPda(jupsolMint),
+        authority: user,
+        payer: user,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([addCollateralInstruction]);
+
+    // 鑄造上限：總量 1,000,000、單一用戶 100,000、每 24 小時 200,000 xxUSD
+    const setMintingLimitsInstruction = await program.methods
+      .setMintingLimits(
+        uiToNative(1000000, 6),
+        uiToNative(100000, 6),
+        uiToNative(200000, 6),
+        new BN(86400)
+      )
+      .accounts({
+        state: programState,
+        authority: user,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([setMintingLimitsInstruction]);
   });
 
   it("Deposits asset successfully", async () => {
@@ -194,18 +320,19 @@ describe("asset_manager", () => {
 
     // Derive PDA for userDeposit
     const [userDepositPdaDerived, userDepositBump] = await PublicKey.findProgramAddress(
-      [Buffer.from("user_deposit"), user.toBuffer()],
+      [Buffer.from("user_deposit"), user.toBuffer(), jupsolMint.toBuffer()],
       program.programId
     );
     userDepositPda = userDepositPdaDerived;
 
-    // Get price from PriceOracle
+    // Refresh LST price from PriceOracle
     const getPriceInstruction = await priceOracleProgram.methods
-      .getPrice("InterestAsset")
+      .refreshLstAsset()
       .accounts({
-        oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed,
-        interestAssetFeed: mockInterestAssetFeed,
+        lstAsset: lstAssetPda(jupsolMint),
+        priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        priceFeed: mockInterestAssetFeed,
+        apyFeed: mockInterestAssetFeed,
       } as any)
       .instruction();
 
@@ -215,10 +342,13 @@ describe("asset_manager", () => {
         user: user,
         userAssetAccount: userAssetAccount,
         assetMint: jupsolMint,
+        collateral: collateralPda(jupsolMint),
         vaultAssetAccount: vaultAssetAccount,
         userDeposit: userDepositPda,
         state: programState,
-        oracle: oracleAccount.publicKey,
+        lstAsset: lstAssetPda(jupsolMint),
+        priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        accessControl: accessControlPDA,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       } as any)
@@ -232,75 +362,208 @@ describe("asset_manager", () => {
     );
     expect(userDepositAccount.amount.toNumber()).to.be.above(0);
 
+    expect(userDepositAccount.collateralAmount.toNumber()).to.equal(depositAmount.toNumber());
+
     const vaultBalance = await connection.getTokenAccountBalance(vaultAssetAccount);
     expect(vaultBalance.value.uiAmount).to.equal(0.1);
+
+    const collateralAccount = await program.account.collateralConfig.fetch(collateralPda(jupsolMint));
+    expect(collateralAccount.totalDeposited.toNumber()).to.equal(depositAmount.toNumber());
+  });
+
+  it("Rejects deposits of a disabled collateral", async () => {
+    const disableInstruction = await program.methods
+      .updateCollateral(8000, uiToNative(1000, 9), false)
+      .accounts({
+        state: programState,
+        collateral: collateralPda(jupsolMint),
+        authority: user,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([disableInstruction]);
+
+    try {
+      const depositInstruction = await program.methods
+        .depositAsset(uiToNative(0.1, 9))
+        .accounts({
+          user: user,
+          userAssetAccount: userAssetAccount,
+          assetMint: jupsolMint,
+          collateral: collateralPda(jupsolMint),
+          vaultAssetAccount: vaultAssetAccount,
+          userDeposit: userDepositPda,
+          state: programState,
+          lstAsset: lstAssetPda(jupsolMint),
+          priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+          accessControl: accessControlPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .instruction();
+
+      await createAndSendV0Tx([depositInstruction]);
+      expect.fail("Expected an error to be thrown");
+    } catch (error: any) {
+      expect(error.toString()).to.not.include("Expected an error to be thrown");
+    }
+
+    const enableInstruction = await program.methods
+      .updateCollateral(8000, uiToNative(1000, 9), true)
+      .accounts({
+        state: programState,
+        collateral: collateralPda(jupsolMint),
+        authority: user,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([enableInstruction]);
   });
 
   it("Mints and distributes xxUSD successfully", async () => {
-    const assetValue = new BN(1000000); // 1,000,000
-    const productPrice = new BN(500000); // 500,000
     const [userDepositPdaDerived] = await PublicKey.findProgramAddressSync(
-      [Buffer.from("user_deposit"), user.toBuffer()],
+      [Buffer.from("user_deposit"), user.toBuffer(), jupsolMint.toBuffer()],
       program.programId
     );
     userDepositPda = userDepositPdaDerived;
+    const depositBefore = await program.account.userDeposit.fetch(userDepositPda);
+    const state = await program.account.programState.fetch(programState);
+    const lockedAmount = state.productPrice.mul(new BN(1000000));
+
+    const lockId = await nextLockId(user);
 
     const mintAndDistributeInstruction = await program.methods
-      .mintAndDistributeXxusd(assetValue, productPrice)
+      .mintAndDistributeXxusd()
       .accounts({
         user: user,
         xxusdMint: usdcMint,
         xxusdVault: xxusdVaultAccount,
         userXxusdAccount: userXxusdAccount,
         userDeposit: userDepositPda,
+        userMint: userMintPda(user),
         state: programState,
+        collateral: collateralPda(jupsolMint),
+        lstAsset: lstAssetPda(jupsolMint),
+        priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        accessControl: accessControlPDA,
+        lockVault: lockVaultAccount,
+        lockManager: lockManagerPda,
+        lockIndex: lockIndexPda(user),
+        lockRecord: lockRecordPda(user, lockId),
+        lockManagerProgram: lockManagerProgram.programId,
       } as any)
       .instruction();
 
     await createAndSendV0Tx([mintAndDistributeInstruction]);
 
-    // Verify minting and distribution
-    const userXxusdBalance = await connection.getTokenAccountBalance(userXxusdAccount);
-    expect(userXxusdBalance.value.uiAmount).to.equal(0.5);
-
+    // 鑄造量由存款價值決定，商品價格的部分直接鎖入 lock_manager
+    const mintable = depositBefore.amount.sub(depositBefore.xxusdAmount);
     const vaultXxusdBalance = await connection.getTokenAccountBalance(xxusdVaultAccount);
-    expect(vaultXxusdBalance.value.uiAmount).to.equal(0.5);
+    expect(vaultXxusdBalance.value.amount).to.equal("0");
+
+    const lockRecord = await lockManagerProgram.account.lockRecord.fetch(lockRecordPda(user, lockId));
+    expect(lockRecord.owner.toString()).to.equal(user.toString());
+    expect(lockRecord.amount.toString()).to.equal(lockedAmount.toString());
+    expect(lockRecord.dailyRelease.toString()).to.equal(lockedAmount.div(lockRecord.lockPeriod).toString());
+
+    const userXxusdBalance = await connection.getTokenAccountBalance(userXxusdAccount);
+    expect(userXxusdBalance.value.amount).to.equal(mintable.sub(lockedAmount).toString());
 
     const userDepositAccount = await program.account.userDeposit.fetch(userDepositPda);
-    expect(userDepositAccount.xxusdAmount.toNumber()).to.equal(500000);
+    expect(userDepositAccount.xxusdAmount.toString()).to.equal(depositBefore.amount.toString());
+
+    const userMint = await program.account.userMintRecord.fetch(userMintPda(user));
+    expect(userMint.outstanding.toString()).to.equal(depositBefore.amount.toString());
   });
 
-  it("Updates APY successfully", async () => {
+  it("Withdraws collateral in proportion to repaid xxUSD", async () => {
+    const before = await program.account.userDeposit.fetch(userDepositPda);
+    const repayAmount = before.xxusdAmount.divn(2);
+
+    const withdrawInstruction = await program.methods
+      .withdrawAsset(repayAmount)
+      .accounts({
+        user: user,
+        userAssetAccount: userAssetAccount,
+        assetMint: jupsolMint,
+        collateral: collateralPda(jupsolMint),
+        vaultAssetAccount: vaultAssetAccount,
+        userDeposit: userDepositPda,
+        userXxusdAccount: userXxusdAccount,
+        xxusdMint: usdcMint,
+        mintAuthority: mintAuthority,
+        userMint: userMintPda(user),
+        state: programState,
+        lstAsset: lstAssetPda(jupsolMint),
+        priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        accessControl: accessControlPDA,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([withdrawInstruction]);
+
+    const after = await program.account.userDeposit.fetch(userDepositPda);
+    expect(after.xxusdAmount.toNumber()).to.equal(before.xxusdAmount.sub(repayAmount).toNumber());
+    expect(after.collateralAmount.toNumber()).to.be.below(before.collateralAmount.toNumber());
+  });
+
+  it("Queues an APY update that cannot execute before the timelock", async () => {
     const newApy = new BN(800); // 8%
 
-    const updateApyInstruction = await program.methods
-      .updateApy(newApy)
+    const queueApyInstruction = await program.methods
+      .queueApyUpdate(newApy)
       .accounts({
         state: programState,
         authority: user,
       } as any)
       .instruction();
 
-    await createAndSendV0Tx([updateApyInstruction]);
-
-    const updatedState = await program.account.programState.fetch(programState);
-    expect(updatedState.currentApy.toNumber()).to.equal(800);
+    await createAndSendV0Tx([queueApyInstruction]);
+
+    const queuedState = await program.account.programState.fetch(programState);
+    expect(queuedState.pendingApy.newValue.toNumber()).to.equal(800);
+    expect(queuedState.currentApy.toNumber()).to.equal(762);
+
+    try {
+      const executeInstruction = await program.methods
+        .executeQueuedUpdate({ apy: {} })
+        .accounts({
+          state: programState,
+          executor: user,
+        } as any)
+        .instruction();
+
+      await createAndSendV0Tx([executeInstruction]);
+      expect.fail("Expected an error to be thrown");
+    } catch (error: any) {
+      expect(error.toString()).to.not.include("Expected an error to be thrown");
+    }
   });
 
-  it("Sets product price successfully", async () => {
+  it("Cancels a queued product price update", async () => {
     const newPrice = new BN(2000); // New price: 2,000
 
-    const setProductPriceInstruction = await program.methods
-      .setProductPrice(newPrice)
+    const queuePriceInstruction = await program.methods
+      .queuePriceUpdate(newPrice)
+      .accounts({
+        state: programState,
+        authority: user,
+      } as any)
+      .instruction();
+
+    const cancelInstruction = await program.methods
+      .cancelQueuedUpdate({ productPrice: {} })
       .accounts({
         state: programState,
         authority: user,
       } as any)
       .instruction();
 
-    await createAndSendV0Tx([setProductPriceInstruction]);
+    await createAndSendV0Tx([queuePriceInstruction, cancelInstruction]);
 
     const updatedState = await program.account.programState.fetch(programState);
-    expect(updatedState.productPrice.toNumber()).to.equal(2000);
+    expect(updatedState.pendingPrice).to.be.null;
+    expect(updatedState.productPrice.toNumber()).to.equal(1798);
   });
 });
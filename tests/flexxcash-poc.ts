@@ -65,12 +65,16 @@ describe("flexxcash-poc", () => {
 
       await createAndSendV0Tx([getPriceSolInstruction]);
 
-      const solPriceAccount = await priceOracleProgram.account.oracleAccount.fetch(oracleAccount.publicKey);
-      expect(solPriceAccount.cachedPriceSol.toNumber()).to.be.greaterThan(0);
+      const [solFeedConfig] = PublicKey.findProgramAddressSync(
+        [Buffer.from("feed"), oracleAccount.publicKey.toBuffer(), Buffer.from("SOL")],
+        priceOracleProgram.programId
+      );
+      const solPriceAccount = await priceOracleProgram.account.feedConfig.fetch(solFeedConfig);
+      expect(solPriceAccount.cachedPrice.toNumber()).to.be.greaterThan(0);
 
       // TODO: 添加 FlexxcashPoc 使用 SOL 價格的邏輯
       // 例如：使用獲取的 SOL 價格來執行某些操作
-      console.log("Current SOL price:", solPriceAccount.cachedPriceSol.toString());
+      console.log("Current SOL price:", solPriceAccount.cachedPrice.toString());
 
       // 使用 PriceOracle 獲取 InterestAsset 數據
       const getPriceInterestAssetInstruction = await priceOracleProgram.methods
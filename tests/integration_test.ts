@@ -14,6 +14,7 @@ import {
   createAssociatedTokenAccount,
   mintTo,
   getAccount,
+  getAssociatedTokenAddressSync,
 } from "@solana/spl-token";
 
 describe("FlexxCash Integration Tests", () => {
@@ -32,6 +33,50 @@ describe("FlexxCash Integration Tests", () => {
   const mockSolFeed = new PublicKey("GvDMxPzN1sCj7L26YDK2HnMRXEQmQ2aemov8YBtPS7vR");
   const mockInterestAssetFeed = new PublicKey("4NiWaTuje7SVe9DN1vfnX7m1qBC7DnUxwRxbdgEDUGX1");
 
+  // 讀取 feed 時的品質門檻：slot 新鮮度、信賴區間（基點）與最少樣本數
+  const feedGuards = {
+    maxSlotStaleness: new anchor.BN(150),
+    maxConfidenceBps: new anchor.BN(200),
+    minSamples: 1,
+  };
+
+  const feedConfigPda = (symbol: string): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("feed"), oracleAccount.publicKey.toBuffer(), Buffer.from(symbol)],
+      priceOracleProgram.programId
+    )[0];
+
+  const lstAssetPda = (mint: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lst"), oracleAccount.publicKey.toBuffer(), mint.toBuffer()],
+      priceOracleProgram.programId
+    )[0];
+
+  const priceHistoryPda = (source: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("history"), source.toBuffer()],
+      priceOracleProgram.programId
+    )[0];
+
+  const collateralPda = (mint: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral"), mint.toBuffer()],
+      assetManagerProgram.programId
+    )[0];
+
+  const vaultPda = (mint: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), mint.toBuffer()],
+      assetManagerProgram.programId
+    )[0];
+
+  // xxUSD 的鑄幣與金庫權限 PDA
+  const mintAuthorityPda = (): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [assetManagerState.toBuffer()],
+      assetManagerProgram.programId
+    )[0];
+
   let mint: PublicKey;
   let userTokenAccount: PublicKey;
   let hedgingVault: PublicKey;
@@ -39,6 +84,7 @@ describe("FlexxCash Integration Tests", () => {
   let hedgingRecord: PublicKey;
   let accessControlPDA: PublicKey;
   let lockRecord: PublicKey;
+  let lockIndex: PublicKey;
   let assetManagerState: PublicKey;
 
   const HEDGING_AMOUNT = 1000000000; // 1 token, 9 decimals
@@ -89,13 +135,26 @@ describe("FlexxCash Integration Tests", () => {
         .accounts({
           oracleAccount: oracleAccount.publicKey,
           authority: provider.wallet.publicKey,
-          solFeed: mockSolFeed,
-          interestAssetFeed: mockInterestAssetFeed,
           systemProgram: SystemProgram.programId,
         } as any)
         .instruction();
 
       await createAndSendV0Tx([initializeOracleInstruction], [oracleAccount]);
+
+      // 註冊 SOL 的數據源
+      const addSolFeedInstruction = await priceOracleProgram.methods
+        .addFeed("SOL", new anchor.BN(60), 0, feedGuards)
+        .accounts({
+          oracleAccount: oracleAccount.publicKey,
+          feedConfig: feedConfigPda("SOL"),
+          priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+          feed: mockSolFeed,
+          authority: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .instruction();
+
+      await createAndSendV0Tx([addSolFeedInstruction]);
       console.log("Oracle account initialized successfully");
     } catch (error) {
       console.error("Failed to initialize Oracle account:", error);
@@ -111,6 +170,23 @@ describe("FlexxCash Integration Tests", () => {
       9
     );
 
+    // 將存入資產註冊為 LST，價格由其自身的數據源提供
+    const addLstAssetInstruction = await priceOracleProgram.methods
+      .addLstAsset("jupSOL", new anchor.BN(60), feedGuards)
+      .accounts({
+        oracleAccount: oracleAccount.publicKey,
+        lstAsset: lstAssetPda(mint),
+        priceHistory: priceHistoryPda(lstAssetPda(mint)),
+        mint: mint,
+        priceFeed: mockInterestAssetFeed,
+        apyFeed: mockInterestAssetFeed,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([addLstAssetInstruction]);
+
     // Create user token account
     userTokenAccount = await createAssociatedTokenAccount(
       provider.connection,
@@ -160,13 +236,14 @@ describe("FlexxCash Integration Tests", () => {
     await createAndSendV0Tx([initializeHedgingStateInstruction]);
 
     // Initialize AccessControl
-    [accessControlPDA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("access-control"), user.toBuffer()],
+    let accessControlBump: number;
+    [accessControlPDA, accessControlBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("access_control")],
       accessControlProgram.programId
     );
 
     const initializeAccessControlInstruction = await accessControlProgram.methods
-      .initialize()
+      .initialize(accessControlBump)
       .accounts({
         accessControl: accessControlPDA,
         admin: user,
@@ -176,8 +253,12 @@ describe("FlexxCash Integration Tests", () => {
     await createAndSendV0Tx([initializeAccessControlInstruction]);
 
     // Initialize LockManager
+    [lockIndex] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock_index"), user.toBuffer()],
+      lockManagerProgram.programId
+    );
     [lockRecord] = PublicKey.findProgramAddressSync(
-      [Buffer.from("lock_record"), user.toBuffer()],
+      [Buffer.from("lock_record"), user.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
       lockManagerProgram.programId
     );
 
@@ -188,14 +269,34 @@ describe("FlexxCash Integration Tests", () => {
     );
 
     const initializeAssetManagerInstruction = await assetManagerProgram.methods
-      .initialize(mint)
+      .initialize(oracleAccount.publicKey)
       .accounts({
         state: assetManagerState,
+        xxusdMint: mint,
+        mintAuthority: mintAuthorityPda(),
+        xxusdVault: getAssociatedTokenAddressSync(mint, mintAuthorityPda(), true),
+        accessControl: accessControlPDA,
         authority: user,
       } as any)
       .instruction();
 
-    await createAndSendV0Tx([initializeAssetManagerInstruction]);
+    // 註冊存入資產為抵押品，並建立其金庫
+    const addCollateralInstruction = await assetManagerProgram.methods
+      .addCollateral(8000, new anchor.BN(HEDGING_AMOUNT))
+      .accounts({
+        state: assetManagerState,
+        assetMint: mint,
+        collateral: collateralPda(mint),
+        vaultAssetAccount: vaultPda(mint),
+        lstAsset: lstAssetPda(mint),
+        authority: user,
+        payer: user,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([initializeAssetManagerInstruction, addCollateralInstruction]);
   });
 
   it("Integrates PriceOracle with HedgingStrategy", async () => {
@@ -204,8 +305,9 @@ describe("FlexxCash Integration Tests", () => {
       .getPrice("SOL")
       .accounts({
         oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed,
-        interestAssetFeed: mockInterestAssetFeed,
+        feedConfig: feedConfigPda("SOL"),
+        priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+        feed: mockSolFeed,
       } as any)
       .instruction();
 
@@ -218,6 +320,7 @@ describe("FlexxCash Integration Tests", () => {
         hedgingVault,
         hedgingRecord,
         systemState,
+        accessControl: accessControlPDA,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       } as any)
@@ -239,9 +342,9 @@ describe("FlexxCash Integration Tests", () => {
   });
 
   it("Integrates AccessControl with LockManager", async () => {
-    // Set permissions
+    // Grant role
     const setPermissionsInstruction = await accessControlProgram.methods
-      .setPermissions("LOCK_MANAGER", true)
+      .grantRole({ minter: {} }, user)
       .accounts({
         accessControl: accessControlPDA,
         admin: user,
@@ -257,7 +360,9 @@ describe("FlexxCash Integration Tests", () => {
         xxusdMint: mint,
         lockVault: hedgingVault,
         lockManager: lockManagerProgram.programId,
+        lockIndex: lockIndex,
         lockRecord: lockRecord,
+        accessControl: accessControlPDA,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
@@ -274,13 +379,14 @@ describe("FlexxCash Integration Tests", () => {
   });
 
   it("Integrates AssetManager with PriceOracle", async () => {
-    // Get InterestAsset data
+    // Refresh LST price from PriceOracle
     const getPriceInstruction = await priceOracleProgram.methods
-      .getPrice("InterestAsset")
+      .refreshLstAsset()
       .accounts({
-        oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed,
-        interestAssetFeed: mockInterestAssetFeed,
+        lstAsset: lstAssetPda(mint),
+        priceHistory: priceHistoryPda(lstAssetPda(mint)),
+        priceFeed: mockInterestAssetFeed,
+        apyFeed: mockInterestAssetFeed,
       } as any)
       .instruction();
 
@@ -292,8 +398,12 @@ describe("FlexxCash Integration Tests", () => {
         user: user,
         userAssetAccount: userTokenAccount,
         assetMint: mint,
-        vaultAssetAccount: hedgingVault,
+        collateral: collateralPda(mint),
+        vaultAssetAccount: vaultPda(mint),
         state: assetManagerState,
+        lstAsset: lstAssetPda(mint),
+        priceHistory: priceHistoryPda(lstAssetPda(mint)),
+        accessControl: accessControlPDA,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       } as any)
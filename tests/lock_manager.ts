@@ -1,6 +1,7 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { LockManager } from "../target/types/lock_manager";
+import { AccessControl } from "../target/types/access_control";
 import { expect } from "chai";
 import {
   PublicKey,
@@ -22,6 +23,7 @@ describe("lock_manager", () => {
   anchor.setProvider(provider);
 
   const program = anchor.workspace.LockManager as Program<LockManager>;
+  const accessControlProgram = anchor.workspace.AccessControl as Program<AccessControl>;
   const user = provider.wallet.publicKey;
 
   let xxusdMint: PublicKey;
@@ -29,11 +31,15 @@ describe("lock_manager", () => {
   let lockVault: PublicKey;
   let lockManager: PublicKey;
   let lockRecord: PublicKey;
+  let lockIndex: PublicKey;
   let assetManager: PublicKey;
+  let accessControlPDA: PublicKey;
 
   const LOCK_AMOUNT = new BN(100_000_000); // 100 xxUSD
   const LOCK_PERIOD = new BN(7 * 24 * 60 * 60); // 1 week in seconds
   const DAILY_RELEASE = new BN(14_285_714); // ~14.28 xxUSD per day
+  // 用戶的第一個鎖倉部位
+  const LOCK_ID = new BN(0);
 
   async function createAndSendV0Tx(txInstructions: anchor.web3.TransactionInstruction[], signers: anchor.web3.Keypair[] = []) {
     let latestBlockhash = await provider.connection.getLatestBlockhash("confirmed");
@@ -86,11 +92,19 @@ describe("lock_manager", () => {
       program.programId
     );
     lockVault = await getAssociatedTokenAddress(xxusdMint, lockManager, true);
+    [lockIndex] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lock_index"), user.toBuffer()],
+      program.programId
+    );
     [lockRecord] = PublicKey.findProgramAddressSync(
-      [Buffer.from("lock_record"), user.toBuffer()],
+      [Buffer.from("lock_record"), user.toBuffer(), LOCK_ID.toArrayLike(Buffer, "le", 8)],
       program.programId
     );
-    assetManager = new PublicKey("91hM5ZdHVbH7tH1a21QHRmPEFkHWS532DfcpGPBUkdAF");
+    assetManager = new PublicKey("HiudtiK3QwUEiRWU3WB7h1azKvu7kpwbZyU1qBvaUUD6");
+    [accessControlPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("access_control")],
+      accessControlProgram.programId
+    );
 
     // Mint some xxUSD to user
     await mintTo(
@@ -112,7 +126,9 @@ describe("lock_manager", () => {
         xxusdMint: xxusdMint,
         lockVault: lockVault,
         lockManager: lockManager,
+        lockIndex: lockIndex,
         lockRecord: lockRecord,
+        accessControl: accessControlPDA,
         assetManager: assetManager,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -128,6 +144,11 @@ describe("lock_manager", () => {
     expect(lockRecordAccount.amount.eq(LOCK_AMOUNT)).to.be.true;
     expect(lockRecordAccount.lockPeriod.eq(LOCK_PERIOD)).to.be.true;
     expect(lockRecordAccount.dailyRelease.eq(DAILY_RELEASE)).to.be.true;
+    expect(lockRecordAccount.id.eq(LOCK_ID)).to.be.true;
+
+    const lockIndexAccount = await program.account.userLockIndex.fetch(lockIndex);
+    expect(lockIndexAccount.nextId.toNumber()).to.equal(1);
+    expect(lockIndexAccount.openIds.map((id) => id.toNumber())).to.deep.equal([0]);
   });
 
   it("should successfully release daily xxUSD", async () => {
@@ -135,7 +156,7 @@ describe("lock_manager", () => {
     await new Promise(resolve => setTimeout(resolve, 1000));
 
     const releaseInstruction = await program.methods
-      .releaseDailyXxusd()
+      .releaseDailyXxusd(LOCK_ID)
       .accounts({
         user: user,
         userTokenAccount: userXxusdAccount,
@@ -143,6 +164,8 @@ describe("lock_manager", () => {
         lockVault: lockVault,
         lockManager: lockManager,
         lockRecord: lockRecord,
+        lockIndex: lockIndex,
+        accessControl: accessControlPDA,
         tokenProgram: TOKEN_PROGRAM_ID,
       } as any)
       .instruction();
@@ -156,7 +179,7 @@ describe("lock_manager", () => {
 
   it("should check lock status correctly", async () => {
     const lockStatus = await program.methods
-      .checkLockStatus()
+      .checkLockStatus(LOCK_ID)
       .accounts({
         user: user,
         lockRecord: lockRecord,
@@ -171,7 +194,7 @@ describe("lock_manager", () => {
 
   it("should check redemption window correctly", async () => {
     const isWithinWindow = await program.methods
-      .isWithinRedemptionWindow()
+      .isWithinRedemptionWindow(LOCK_ID)
       .accounts({
         user: user,
         lockRecord: lockRecord,
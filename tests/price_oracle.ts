@@ -16,6 +16,32 @@ describe("PriceOracle Tests on Devnet", () => {
 
   const mockSolFeed = Keypair.generate();
   const mockInterestAssetFeed = Keypair.generate();
+  const jupsolMint = Keypair.generate().publicKey;
+
+  // 讀取 feed 時的品質門檻：slot 新鮮度、信賴區間（基點）與最少樣本數
+  const feedGuards = {
+    maxSlotStaleness: new anchor.BN(150),
+    maxConfidenceBps: new anchor.BN(200),
+    minSamples: 1,
+  };
+
+  const feedConfigPda = (symbol: string): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("feed"), oracleAccount.publicKey.toBuffer(), Buffer.from(symbol)],
+      program.programId
+    )[0];
+
+  const lstAssetPda = (mint: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lst"), oracleAccount.publicKey.toBuffer(), mint.toBuffer()],
+      program.programId
+    )[0];
+
+  const priceHistoryPda = (source: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("history"), source.toBuffer()],
+      program.programId
+    )[0];
 
   // 模擬 PullFeedAccountData 結構
   const mockSolFeedData = Buffer.alloc(8 + 8); // 假設包含價格和其他必要字段
@@ -107,13 +133,40 @@ describe("PriceOracle Tests on Devnet", () => {
         .accounts({
           oracleAccount: oracleAccount.publicKey,
           authority: provider.wallet.publicKey,
-          solFeed: mockSolFeed.publicKey,
-          interestAssetFeed: mockInterestAssetFeed.publicKey,
           system_program: SystemProgram.programId,
         } as any)
         .instruction();
 
       await createAndSendV0Tx([initializeInstruction], [oracleAccount]);
+
+      // 註冊 SOL 數據源與 jupSOL 資產
+      const addSolFeedInstruction = await program.methods
+        .addFeed("SOL", new anchor.BN(60), 0, feedGuards)
+        .accounts({
+          oracleAccount: oracleAccount.publicKey,
+          feedConfig: feedConfigPda("SOL"),
+          priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+          feed: mockSolFeed.publicKey,
+          authority: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .instruction();
+
+      const addLstAssetInstruction = await program.methods
+        .addLstAsset("jupSOL", new anchor.BN(60), feedGuards)
+        .accounts({
+          oracleAccount: oracleAccount.publicKey,
+          lstAsset: lstAssetPda(jupsolMint),
+          priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+          mint: jupsolMint,
+          priceFeed: mockInterestAssetFeed.publicKey,
+          apyFeed: mockInterestAssetFeed.publicKey,
+          authority: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .instruction();
+
+      await createAndSendV0Tx([addSolFeedInstruction, addLstAssetInstruction]);
       console.log("Oracle account initialized successfully");
     } catch (error) {
       console.error("Failed to initialize accounts:", error);
@@ -124,11 +177,15 @@ describe("PriceOracle Tests on Devnet", () => {
   it("Initializes the oracle account", async () => {
     const account = await program.account.oracleAccount.fetch(oracleAccount.publicKey);
     expect(account.authority.toString()).to.equal(provider.wallet.publicKey.toString());
-    expect(account.solFeed.toString()).to.equal(mockSolFeed.publicKey.toString());
-    expect(account.interestAssetFeed.toString()).to.equal(mockInterestAssetFeed.publicKey.toString());
-    expect(account.lastUpdateTimestampSol.toNumber()).to.equal(0);
-    expect(account.cachedPriceSol.toNumber()).to.equal(0);
-    expect(account.lastUpdateTimestampInterestAsset.toNumber()).to.equal(0);
+    const solFeedConfig = await program.account.feedConfig.fetch(feedConfigPda("SOL"));
+    expect(solFeedConfig.feed.toString()).to.equal(mockSolFeed.publicKey.toString());
+    const lstAsset = await program.account.lstAsset.fetch(lstAssetPda(jupsolMint));
+    expect(lstAsset.symbol).to.equal("jupSOL");
+    expect(lstAsset.mint.toString()).to.equal(jupsolMint.toString());
+    expect(lstAsset.priceFeed.toString()).to.equal(mockInterestAssetFeed.publicKey.toString());
+    expect(lstAsset.lastUpdateTimestamp.toNumber()).to.equal(0);
+    expect(solFeedConfig.lastUpdateTimestamp.toNumber()).to.equal(0);
+    expect(solFeedConfig.cachedPrice.toNumber()).to.equal(0);
   });
 
   it("Fetches and updates the SOL price successfully", async () => {
@@ -136,93 +193,262 @@ describe("PriceOracle Tests on Devnet", () => {
       .getPrice("SOL")
       .accounts({
         oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed.publicKey,
-        interestAssetFeed: mockInterestAssetFeed.publicKey,
+        feedConfig: feedConfigPda("SOL"),
+        priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+        feed: mockSolFeed.publicKey,
       } as any)
       .instruction();
 
     await createAndSendV0Tx([getPriceInstruction]);
 
-    const updatedAccount = await program.account.oracleAccount.fetch(oracleAccount.publicKey);
-    expect(updatedAccount.cachedPriceSol.toNumber()).to.be.greaterThan(0);
-    expect(updatedAccount.lastUpdateTimestampSol.toNumber()).to.be.greaterThan(0);
+    const updatedFeedConfig = await program.account.feedConfig.fetch(feedConfigPda("SOL"));
+    expect(updatedFeedConfig.cachedPrice.toNumber()).to.be.greaterThan(0);
+    expect(updatedFeedConfig.lastUpdateTimestamp.toNumber()).to.be.greaterThan(0);
   });
 
   it("Uses cached SOL price on subsequent fetch", async () => {
-    const beforeFetch = await program.account.oracleAccount.fetch(oracleAccount.publicKey);
-    const beforeTimestamp = beforeFetch.lastUpdateTimestampSol;
+    const beforeFetch = await program.account.feedConfig.fetch(feedConfigPda("SOL"));
+    const beforeTimestamp = beforeFetch.lastUpdateTimestamp;
 
     const getPriceInstruction = await program.methods
       .getPrice("SOL")
       .accounts({
         oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed.publicKey,
-        interestAssetFeed: mockInterestAssetFeed.publicKey,
+        feedConfig: feedConfigPda("SOL"),
+        priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+        feed: mockSolFeed.publicKey,
       } as any)
       .instruction();
 
     await createAndSendV0Tx([getPriceInstruction]);
 
-    const afterFetch = await program.account.oracleAccount.fetch(oracleAccount.publicKey);
-    expect(afterFetch.cachedPriceSol.toNumber()).to.be.greaterThan(0);
-    expect(afterFetch.lastUpdateTimestampSol.toNumber()).to.equal(beforeTimestamp.toNumber());
+    const afterFetch = await program.account.feedConfig.fetch(feedConfigPda("SOL"));
+    expect(afterFetch.cachedPrice.toNumber()).to.be.greaterThan(0);
+    expect(afterFetch.lastUpdateTimestamp.toNumber()).to.equal(beforeTimestamp.toNumber());
   });
 
-  it("Fetches and updates the Interest Asset data successfully", async () => {
-    const getPriceInstruction = await program.methods
-      .getPrice("InterestAsset")
+  it("Refreshes an LST asset from its own feeds", async () => {
+    const refreshInstruction = await program.methods
+      .refreshLstAsset()
       .accounts({
-        oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed.publicKey,
-        interestAssetFeed: mockInterestAssetFeed.publicKey,
+        lstAsset: lstAssetPda(jupsolMint),
+        priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        priceFeed: mockInterestAssetFeed.publicKey,
+        apyFeed: mockInterestAssetFeed.publicKey,
       } as any)
       .instruction();
 
-    await createAndSendV0Tx([getPriceInstruction]);
+    await createAndSendV0Tx([refreshInstruction]);
 
-    const updatedAccount = await program.account.oracleAccount.fetch(oracleAccount.publicKey);
-    
-    const assets = ["jupsol", "vsol", "bsol", "msol", "hsol", "jitosol"];
-    for (const asset of assets) {
-      expect((updatedAccount as any)[`${asset}Price`]).to.be.greaterThan(0);
-      expect((updatedAccount as any)[`${asset}Apy`]).to.be.greaterThan(0);
-    }
+    const lstAsset = await program.account.lstAsset.fetch(lstAssetPda(jupsolMint));
+    expect(lstAsset.price.toNumber()).to.be.greaterThan(0);
+    expect(lstAsset.apy.toNumber()).to.be.greaterThan(0);
+    expect(lstAsset.lastUpdateTimestamp.toNumber()).to.be.greaterThan(0);
+  });
+
+  it("Uses cached LST data on subsequent refresh within 60 seconds", async () => {
+    const firstFetch = await program.account.lstAsset.fetch(lstAssetPda(jupsolMint));
+    const firstTimestamp = firstFetch.lastUpdateTimestamp;
+
+    const refreshInstruction = await program.methods
+      .refreshLstAsset()
+      .accounts({
+        lstAsset: lstAssetPda(jupsolMint),
+        priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        priceFeed: mockInterestAssetFeed.publicKey,
+        apyFeed: mockInterestAssetFeed.publicKey,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([refreshInstruction]);
+
+    const secondFetch = await program.account.lstAsset.fetch(lstAssetPda(jupsolMint));
+    expect(secondFetch.lastUpdateTimestamp.toNumber()).to.equal(firstTimestamp.toNumber());
+  });
 
-    expect(updatedAccount.lastUpdateTimestampInterestAsset.toNumber()).to.be.greaterThan(0);
+  it("Returns structured price data for an LST asset", async () => {
+    const priceData = await program.methods
+      .getLstPriceData()
+      .accounts({
+        lstAsset: lstAssetPda(jupsolMint),
+      } as any)
+      .view();
+
+    const lstAsset = await program.account.lstAsset.fetch(lstAssetPda(jupsolMint));
+    expect(priceData.price.toNumber()).to.equal(lstAsset.price.toNumber());
+    expect(priceData.exponent).to.equal(-9);
+    expect(priceData.publishTime.toNumber()).to.equal(lstAsset.lastUpdateTimestamp.toNumber());
+    expect(priceData.source).to.deep.equal({ cache: {} });
+  });
+
+  it("Records an observation for each fresh LST price", async () => {
+    const priceHistory = await program.account.priceHistory.fetch(priceHistoryPda(lstAssetPda(jupsolMint)));
+    const lstAsset = await program.account.lstAsset.fetch(lstAssetPda(jupsolMint));
+    expect(priceHistory.symbol).to.equal("jupSOL");
+    expect(priceHistory.observations.length).to.equal(1);
+    expect(priceHistory.observations[0].price.toNumber()).to.equal(lstAsset.price.toNumber());
+  });
+
+  it("Fails to compute a TWAP over a window longer than the recorded history", async () => {
+    try {
+      await program.methods
+        .getTwap("jupSOL", new anchor.BN(3600))
+        .accounts({
+          priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        } as any)
+        .view();
+      expect.fail("Expected an error to be thrown");
+    } catch (error: any) {
+      expect(error.toString()).to.include("InsufficientHistory");
+    }
   });
 
-  it("Uses cached Interest Asset data on subsequent fetch within 60 seconds", async () => {
-    // First fetch
-    const firstFetchInstruction = await program.methods
-      .getPrice("InterestAsset")
+  it("Configures the circuit breaker and rejects acknowledging an active asset", async () => {
+    const configureInstruction = await program.methods
+      .configureCircuitBreaker(new anchor.BN(1000), 3)
       .accounts({
         oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed.publicKey,
-        interestAssetFeed: mockInterestAssetFeed.publicKey,
+        priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+        authority: provider.wallet.publicKey,
       } as any)
       .instruction();
 
-    await createAndSendV0Tx([firstFetchInstruction]);
+    await createAndSendV0Tx([configureInstruction]);
+
+    const priceHistory = await program.account.priceHistory.fetch(priceHistoryPda(lstAssetPda(jupsolMint)));
+    expect(priceHistory.maxMoveBps.toNumber()).to.equal(1000);
+    expect(priceHistory.stabilizationUpdates).to.equal(3);
+    expect(priceHistory.halted).to.be.false;
+
+    try {
+      const acknowledgeInstruction = await program.methods
+        .acknowledgeHalt()
+        .accounts({
+          oracleAccount: oracleAccount.publicKey,
+          priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+          authority: provider.wallet.publicKey,
+        } as any)
+        .instruction();
+
+      await createAndSendV0Tx([acknowledgeInstruction]);
+      expect.fail("Expected an error to be thrown");
+    } catch (error: any) {
+      expect(error.toString()).to.include("NotHalted");
+    }
+  });
+
+  it("Fails to refresh an LST asset with an unregistered feed", async () => {
+    try {
+      const refreshInstruction = await program.methods
+        .refreshLstAsset()
+        .accounts({
+          lstAsset: lstAssetPda(jupsolMint),
+          priceHistory: priceHistoryPda(lstAssetPda(jupsolMint)),
+          priceFeed: mockSolFeed.publicKey,
+          apyFeed: mockInterestAssetFeed.publicKey,
+        } as any)
+        .instruction();
+
+      await createAndSendV0Tx([refreshInstruction]);
+      expect.fail("Expected an error to be thrown");
+    } catch (error: any) {
+      expect(error.toString()).to.include("FeedMismatch");
+    }
+  });
+
+  it("Fails when the feed account does not match the registry", async () => {
+    try {
+      const getPriceInstruction = await program.methods
+        .getPrice("SOL")
+        .accounts({
+          oracleAccount: oracleAccount.publicKey,
+          feedConfig: feedConfigPda("SOL"),
+          priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+          feed: mockInterestAssetFeed.publicKey,
+        } as any)
+        .instruction();
+
+      await createAndSendV0Tx([getPriceInstruction]);
+      expect.fail("Expected an error to be thrown");
+    } catch (error: any) {
+      expect(error.toString()).to.include("FeedMismatch");
+    }
+  });
 
-    const firstFetch = await program.account.oracleAccount.fetch(oracleAccount.publicKey);
-    const firstTimestamp = firstFetch.lastUpdateTimestampInterestAsset;
+  it("Configures fallback sources and pushes a manual price", async () => {
+    const setFallbackInstruction = await program.methods
+      .setFallbackSources([mockInterestAssetFeed.publicKey], new anchor.BN(300), new anchor.BN(500))
+      .accounts({
+        oracleAccount: oracleAccount.publicKey,
+        feedConfig: feedConfigPda("SOL"),
+        authority: provider.wallet.publicKey,
+      } as any)
+      .instruction();
 
-    // Second fetch (should use cached data)
-    const secondFetchInstruction = await program.methods
-      .getPrice("InterestAsset")
+    const pushManualPriceInstruction = await program.methods
+      .pushManualPrice(new anchor.BN(150))
       .accounts({
         oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed.publicKey,
-        interestAssetFeed: mockInterestAssetFeed.publicKey,
+        feedConfig: feedConfigPda("SOL"),
+        authority: provider.wallet.publicKey,
       } as any)
       .instruction();
 
-    await createAndSendV0Tx([secondFetchInstruction]);
+    await createAndSendV0Tx([setFallbackInstruction, pushManualPriceInstruction]);
+
+    const feedConfig = await program.account.feedConfig.fetch(feedConfigPda("SOL"));
+    expect(feedConfig.fallbackFeeds.map((feed) => feed.toString())).to.deep.equal([
+      mockInterestAssetFeed.publicKey.toString(),
+    ]);
+    expect(feedConfig.maxDeviationBps.toNumber()).to.equal(500);
+    expect(feedConfig.manualPrice.toNumber()).to.equal(150);
+    expect(feedConfig.manualPriceTimestamp.toNumber()).to.be.greaterThan(0);
+  });
+
+  it("Fails when a fallback feed duplicates the primary feed", async () => {
+    try {
+      const setFallbackInstruction = await program.methods
+        .setFallbackSources([mockSolFeed.publicKey], new anchor.BN(300), new anchor.BN(500))
+        .accounts({
+          oracleAccount: oracleAccount.publicKey,
+          feedConfig: feedConfigPda("SOL"),
+          authority: provider.wallet.publicKey,
+        } as any)
+        .instruction();
+
+      await createAndSendV0Tx([setFallbackInstruction]);
+      expect.fail("Expected an error to be thrown");
+    } catch (error: any) {
+      expect(error.toString()).to.include("DuplicateFeed");
+    }
+  });
+
+  it("Does not expose mock feed instructions", async () => {
+    // 模擬 feed 由獨立的 mock_oracle 程式提供，price_oracle 的 IDL 不應包含任何寫入 feed 的指令
+    const instructionNames = program.idl.instructions.map((ix) => ix.name);
+    expect(instructionNames).to.not.include("init_mock_feed");
+    expect(instructionNames).to.not.include("set_mock_feed");
+  });
 
-    const secondFetch = await program.account.oracleAccount.fetch(oracleAccount.publicKey);
-    const secondTimestamp = secondFetch.lastUpdateTimestampInterestAsset;
+  it("Fails to register a feed without a minimum sample requirement", async () => {
+    try {
+      const addFeedInstruction = await program.methods
+        .addFeed("BTC", new anchor.BN(60), 0, { ...feedGuards, minSamples: 0 })
+        .accounts({
+          oracleAccount: oracleAccount.publicKey,
+          feedConfig: feedConfigPda("BTC"),
+          priceHistory: priceHistoryPda(feedConfigPda("BTC")),
+          feed: mockSolFeed.publicKey,
+          authority: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .instruction();
 
-    expect(secondTimestamp.toNumber()).to.equal(firstTimestamp.toNumber());
+      await createAndSendV0Tx([addFeedInstruction]);
+      expect.fail("Expected an error to be thrown");
+    } catch (error: any) {
+      expect(error.toString()).to.include("InvalidGuards");
+    }
   });
 
   it("Fails to fetch price for invalid asset", async () => {
@@ -231,8 +457,9 @@ describe("PriceOracle Tests on Devnet", () => {
         .getPrice("INVALID")
         .accounts({
           oracleAccount: oracleAccount.publicKey,
-          solFeed: mockSolFeed.publicKey,
-          interestAssetFeed: mockInterestAssetFeed.publicKey,
+          feedConfig: feedConfigPda("SOL"),
+          priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+          feed: mockSolFeed.publicKey,
         } as any)
         .instruction();
 
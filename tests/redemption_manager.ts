@@ -3,6 +3,8 @@ import { Program } from "@coral-xyz/anchor";
 import { RedemptionManager } from "../target/types/redemption_manager";
 import { PriceOracle } from "../target/types/price_oracle";
 import { AccessControl } from "../target/types/access_control";
+import { LockManager } from "../target/types/lock_manager";
+import { AssetManager } from "../target/types/asset_manager";
 import { expect } from "chai";
 import {
   PublicKey,
@@ -40,7 +42,11 @@ describe("redemption_manager", () => {
   const redemptionManagerProgram = anchor.workspace.RedemptionManager as Program<RedemptionManager>;
   const priceOracleProgram = anchor.workspace.PriceOracle as Program<PriceOracle>;
   const accessControlProgram = anchor.workspace.AccessControl as Program<AccessControl>;
+  const lockManagerProgram = anchor.workspace.LockManager as Program<LockManager>;
+  const assetManagerProgram = anchor.workspace.AssetManager as Program<AssetManager>;
   const user = provider.wallet.publicKey;
+  // 贖回針對的鎖倉部位
+  const LOCK_ID = new BN(0);
 
   // PDA 變數宣告
   let xxusdMint: PublicKey;
@@ -56,11 +62,42 @@ describe("redemption_manager", () => {
   let redemptionRequestBump: number;
   let redemptionManagerPDA: PublicKey;
   let redemptionManagerBump: number;
+  // 鑄造 xxUSD 時使用的 asset_manager 狀態帳戶，贖回銷毀需回報給它
+  let assetManagerState: PublicKey;
 
   const MINIMUM_XXUSD_BALANCE = 100_000_000_000; // 使用 number 類型
   const mockSolFeed = new PublicKey("GvDMxPzN1sCj7L26YDK2HnMRXEQmQ2aemov8YBtPS7vR");
   const mockInterestAssetFeed = new PublicKey("4NiWaTuje7SVe9DN1vfnX7m1qBC7DnUxwRxbdgEDUGX1");
 
+  // 讀取 feed 時的品質門檻：slot 新鮮度、信賴區間（基點）與最少樣本數
+  const feedGuards = {
+    maxSlotStaleness: new anchor.BN(150),
+    maxConfidenceBps: new anchor.BN(200),
+    minSamples: 1,
+  };
+
+  const feedConfigPda = (symbol: string): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("feed"), oracleAccount.publicKey.toBuffer(), Buffer.from(symbol)],
+      priceOracleProgram.programId
+    )[0];
+
+  const assetManagerPda = (seeds: Buffer[]): PublicKey =>
+    PublicKey.findProgramAddressSync(seeds, assetManagerProgram.programId)[0];
+
+  // 每個鎖倉部位累計贖回數量的紀錄
+  const lockRedemptionPda = (): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lock_redemption"), user.toBuffer(), LOCK_ID.toArrayLike(Buffer, "le", 8)],
+      redemptionManagerProgram.programId
+    )[0];
+
+  const priceHistoryPda = (source: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("history"), source.toBuffer()],
+      priceOracleProgram.programId
+    )[0];
+
   async function createAndSendV0Tx(txInstructions: anchor.web3.TransactionInstruction[], signers: anchor.web3.Keypair[] = []) {
     let latestBlockhash = await provider.connection.getLatestBlockhash("confirmed");
     console.log("   ✅ - Fetched latest blockhash. Last valid block height:", latestBlockhash.lastValidBlockHeight);
@@ -147,7 +184,7 @@ describe("redemption_manager", () => {
     console.log("Initializing AccessControl account...");
     try {
       [accessControlPDA, accessControlBump] = await PublicKey.findProgramAddress(
-        [Buffer.from("access_control"), adminKeypair.publicKey.toBuffer()],
+        [Buffer.from("access_control")],
         accessControlProgram.programId
       );
       console.log("AccessControl PDA:", accessControlPDA.toBase58());
@@ -217,7 +254,7 @@ describe("redemption_manager", () => {
   const initializeRedemptionRequest = async () => {
     console.log("Initializing RedemptionRequest PDA...");
     [redemptionRequestPDA, redemptionRequestBump] = await PublicKey.findProgramAddress(
-      [Buffer.from("redemption_request"), user.toBuffer()],
+      [Buffer.from("redemption_request"), user.toBuffer(), LOCK_ID.toArrayLike(Buffer, "le", 8)],
       redemptionManagerProgram.programId
     );
     console.log("RedemptionRequest PDA:", redemptionRequestPDA.toBase58());
@@ -229,14 +266,19 @@ describe("redemption_manager", () => {
       if (accountInfo === null) {
         console.log("Creating RedemptionRequest account...");
         const tx = await redemptionManagerProgram.methods
-          .initiateRedeem(new BN(0)) // 使用 BN 類型
+          .initiateRedeem(LOCK_ID, new BN(0)) // 使用 BN 類型
           .accounts({
             user: user,
             userTokenAccount: userXxusdAccount,
             redemptionVault: redemptionVault,
+            assetManagerState: assetManagerState,
+            xxusdMint: xxusdMint,
+            redemptionManager: redemptionManagerPDA,
             lockRecord: lockRecordPDA,
             redemptionRequest: redemptionRequestPDA,
+            lockRedemption: lockRedemptionPda(),
             systemState: systemState,
+            accessControl: accessControlPDA,
             tokenProgram: TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
           } as any)
@@ -263,6 +305,36 @@ describe("redemption_manager", () => {
     }
   };
 
+  // 初始化 PriceOracle 並註冊 SOL 數據源，贖回時需要檢查其熔斷狀態
+  const initializePriceOracle = async () => {
+    oracleAccount = Keypair.generate();
+
+    const initializeOracleInstruction = await priceOracleProgram.methods
+      .initialize()
+      .accounts({
+        oracleAccount: oracleAccount.publicKey,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([initializeOracleInstruction], [oracleAccount]);
+
+    const addSolFeedInstruction = await priceOracleProgram.methods
+      .addFeed("SOL", new anchor.BN(60), 0, feedGuards)
+      .accounts({
+        oracleAccount: oracleAccount.publicKey,
+        feedConfig: feedConfigPda("SOL"),
+        priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+        feed: mockSolFeed,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([addSolFeedInstruction]);
+  };
+
   // 初始化 RedemptionManager PDA
   const initializeRedemptionManager = async () => {
     console.log("Initializing RedemptionManager PDA...");
@@ -272,6 +344,7 @@ describe("redemption_manager", () => {
     );
     console.log("RedemptionManager PDA:", redemptionManagerPDA.toBase58());
     console.log("RedemptionManager Bump:", redemptionManagerBump);
+    assetManagerState = assetManagerPda([Buffer.from("state")]);
 
     // Initialize RedemptionManager account if necessary
     try {
@@ -279,7 +352,7 @@ describe("redemption_manager", () => {
       if (accountInfo === null) {
         console.log("Creating RedemptionManager account...");
         const tx = await redemptionManagerProgram.methods
-          .initializeSystemState()
+          .initializeSystemState(oracleAccount.publicKey)
           .accounts({
             systemState: systemState,
             authority: adminKeypair.publicKey,
@@ -290,6 +363,18 @@ describe("redemption_manager", () => {
 
         await provider.connection.confirmTransaction(tx, "confirmed");
 
+        // 贖回以此 PDA 的 SOL 出金，注資時建立為程式擁有的帳戶
+        const fundTx = await redemptionManagerProgram.methods
+          .fundRedemptionPool(new BN(10 * LAMPORTS_PER_SOL))
+          .accounts({
+            funder: adminKeypair.publicKey,
+            redemptionManager: redemptionManagerPDA,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([adminKeypair])
+          .rpc();
+        await provider.connection.confirmTransaction(fundTx, "confirmed");
+
         const createdAccountInfo = await provider.connection.getAccountInfo(redemptionManagerPDA);
         if (createdAccountInfo === null) {
           throw new Error("Failed to create RedemptionManager account");
@@ -305,12 +390,20 @@ describe("redemption_manager", () => {
   };
 
   before(async () => {
+    // SystemState 為固定種子的 PDA，所有指令都只接受這一個
+    [systemState] = PublicKey.findProgramAddressSync(
+      [Buffer.from("system_state")],
+      redemptionManagerProgram.programId
+    );
+
     // 初始化 AccessControl PDA 和帳戶
     await closeAccountIfExists(accessControlPDA);
     await ensureAccessControlInitialized();
 
-    // 創建並初始化 LockRecord
-    await createAndInitializeLockRecord();
+    // 贖回讀取 lock_manager 的鎖倉部位
+    await deriveLockRecord();
+
+    await initializePriceOracle();
 
     // 初始化 RedemptionRequest 和 RedemptionManager PDA
     await initializeRedemptionRequest();
@@ -319,21 +412,21 @@ describe("redemption_manager", () => {
 
   it("Verifies the correct PDA is generated", async () => {
     const [expectedAccessControlPDA, expectedAccessControlBump] = await PublicKey.findProgramAddress(
-      [Buffer.from("access_control"), adminKeypair.publicKey.toBuffer()],
+      [Buffer.from("access_control")],
       accessControlProgram.programId
     );
     expect(accessControlPDA.toString()).to.equal(expectedAccessControlPDA.toString());
     expect(accessControlBump).to.equal(expectedAccessControlBump);
 
     const [expectedLockRecordPDA, expectedLockRecordBump] = await PublicKey.findProgramAddress(
-      [Buffer.from("lock_record"), user.toBuffer()],
-      redemptionManagerProgram.programId
+      [Buffer.from("lock_record"), user.toBuffer(), LOCK_ID.toArrayLike(Buffer, "le", 8)],
+      lockManagerProgram.programId
     );
     expect(lockRecordPDA.toString()).to.equal(expectedLockRecordPDA.toString());
     expect(lockRecordBump).to.equal(expectedLockRecordBump);
 
     const [expectedRedemptionRequestPDA, expectedRedemptionRequestBump] = await PublicKey.findProgramAddress(
-      [Buffer.from("redemption_request"), user.toBuffer()],
+      [Buffer.from("redemption_request"), user.toBuffer(), LOCK_ID.toArrayLike(Buffer, "le", 8)],
       redemptionManagerProgram.programId
     );
     expect(redemptionRequestPDA.toString()).to.equal(expectedRedemptionRequestPDA.toString());
@@ -355,7 +448,7 @@ describe("redemption_manager", () => {
 
       expect(accessControlAccount.admin.toString()).to.equal(adminKeypair.publicKey.toString());
       expect(accessControlAccount.is_paused).to.be.false;
-      expect(accessControlAccount.permissions.length).to.equal(0);
+      expect(accessControlAccount.roles.length).to.equal(0);
     } catch (error) {
       console.error("Initialization error:", error);
       if (error instanceof anchor.AnchorError) {
@@ -375,14 +468,19 @@ describe("redemption_manager", () => {
 
     const redeemAmount = MINIMUM_XXUSD_BALANCE / 2;
     const initiateRedeemInstruction = await redemptionManagerProgram.methods
-      .initiateRedeem(new BN(redeemAmount)) // 使用 BN 類型
+      .initiateRedeem(LOCK_ID, new BN(redeemAmount)) // 使用 BN 類型
       .accounts({
         user: user,
         userTokenAccount: userXxusdAccount,
         redemptionVault: redemptionVault,
+        assetManagerState: assetManagerState,
+        xxusdMint: xxusdMint,
+        redemptionManager: redemptionManagerPDA,
         lockRecord: lockRecordPDA,
         redemptionRequest: redemptionRequestPDA,
+        lockRedemption: lockRedemptionPda(),
         systemState: systemState,
+        accessControl: accessControlPDA,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       } as any)
@@ -402,19 +500,30 @@ describe("redemption_manager", () => {
       .getPrice("SOL")
       .accounts({
         oracleAccount: oracleAccount.publicKey,
-        solFeed: mockSolFeed,
-        interestAssetFeed: mockInterestAssetFeed,
+        feedConfig: feedConfigPda("SOL"),
+        priceHistory: priceHistoryPda(feedConfigPda("SOL")),
+        feed: mockSolFeed,
       } as any)
       .instruction();
 
     const executeRedeemInstruction = await redemptionManagerProgram.methods
-      .executeRedeem()
+      .executeRedeem(LOCK_ID)
       .accounts({
         user: user,
         redemptionVault: redemptionVault,
         lockRecord: lockRecordPDA,
         redemptionRequest: redemptionRequestPDA,
         systemState: systemState,
+        accessControl: accessControlPDA,
+        solPriceHistory: priceHistoryPda(feedConfigPda("SOL")),
+        oracleAccount: oracleAccount.publicKey,
+        solFeedConfig: feedConfigPda("SOL"),
+        solFeed: mockSolFeed,
+        priceOracleProgram: priceOracleProgram.programId,
+        assetManagerState: assetManagerState,
+        xxusdMintAuthority: assetManagerPda([assetManagerState.toBuffer()]),
+        userMint: assetManagerPda([Buffer.from("user_mint"), user.toBuffer()]),
+        assetManagerProgram: assetManagerProgram.programId,
         xxusdMint: xxusdMint,
         redemptionManager: redemptionManagerPDA,
         tokenProgram: TOKEN_PROGRAM_ID,
@@ -444,13 +553,23 @@ describe("redemption_manager", () => {
 
     try {
       const executeRedeemInstruction = await redemptionManagerProgram.methods
-        .executeRedeem()
+        .executeRedeem(LOCK_ID)
         .accounts({
           user: user,
           redemptionVault: redemptionVault,
           lockRecord: lockRecordPDA,
           redemptionRequest: redemptionRequestPDA,
           systemState: systemState,
+          accessControl: accessControlPDA,
+          solPriceHistory: priceHistoryPda(feedConfigPda("SOL")),
+          oracleAccount: oracleAccount.publicKey,
+          solFeedConfig: feedConfigPda("SOL"),
+          solFeed: mockSolFeed,
+          priceOracleProgram: priceOracleProgram.programId,
+          assetManagerState: assetManagerState,
+          xxusdMintAuthority: assetManagerPda([assetManagerState.toBuffer()]),
+          userMint: assetManagerPda([Buffer.from("user_mint"), user.toBuffer()]),
+          assetManagerProgram: assetManagerProgram.programId,
           xxusdMint: xxusdMint,
           redemptionManager: redemptionManagerPDA,
           tokenProgram: TOKEN_PROGRAM_ID,
@@ -477,7 +596,7 @@ describe("redemption_manager", () => {
 
   it("should check redeem eligibility correctly", async () => {
     const eligibility = await redemptionManagerProgram.methods
-      .checkRedeemEligibility()
+      .checkRedeemEligibility(LOCK_ID)
       .accounts({
         user: user,
         userTokenAccount: userXxusdAccount,
@@ -489,6 +608,25 @@ describe("redemption_manager", () => {
     expect(typeof eligibility).to.equal('boolean');
   });
 
+  it("Closes the processed redemption request and returns its rent", async () => {
+    const requestRent = await provider.connection.getBalance(redemptionRequestPDA);
+    expect(requestRent).to.be.greaterThan(0);
+
+    const closeInstruction = await redemptionManagerProgram.methods
+      .closeRedemptionRequest(LOCK_ID)
+      .accounts({
+        user: user,
+        redemptionRequest: redemptionRequestPDA,
+      } as any)
+      .instruction();
+
+    await createAndSendV0Tx([closeInstruction]);
+
+    // 帳戶關閉後同一部位的 seed 可再次用於發起贖回
+    const accountInfo = await provider.connection.getAccountInfo(redemptionRequestPDA);
+    expect(accountInfo).to.be.null;
+  });
+
   it("Activates emergency stop successfully", async () => {
     try {
       await accessControlProgram.methods
@@ -603,58 +741,23 @@ describe("redemption_manager", () => {
 
   after(async () => {
     await closeAccountIfExists(accessControlPDA);
-    await closeAccountIfExists(lockRecordPDA);
     await closeAccountIfExists(redemptionRequestPDA);
     await closeAccountIfExists(redemptionManagerPDA);
   });
 
   // 定義帳戶初始化和獲取函數
-  const createAndInitializeLockRecord = async () => {
-    console.log("Creating and initializing LockRecord PDA...");
+  // 鎖倉部位由 lock_manager 在鑄造時建立，這裡只推導其位址並確認存在
+  const deriveLockRecord = async () => {
     [lockRecordPDA, lockRecordBump] = await PublicKey.findProgramAddress(
-      [Buffer.from("lock_record"), user.toBuffer()],
-      redemptionManagerProgram.programId
+      [Buffer.from("lock_record"), user.toBuffer(), LOCK_ID.toArrayLike(Buffer, "le", 8)],
+      lockManagerProgram.programId
     );
     console.log("LockRecord PDA:", lockRecordPDA.toBase58());
-    console.log("LockRecord Bump:", lockRecordBump);
 
-    // Initialize LockRecord account
-    try {
-      const accountInfo = await provider.connection.getAccountInfo(lockRecordPDA);
-      if (accountInfo === null) {
-        console.log("Creating LockRecord account...");
-        const tx = await redemptionManagerProgram.methods
-          .initiateRedeem(new BN(0)) // 使用 BN 類型
-          .accounts({
-            user: user,
-            userTokenAccount: userXxusdAccount,
-            redemptionVault: redemptionVault,
-            lockRecord: lockRecordPDA,
-            redemptionRequest: redemptionRequestPDA,
-            systemState: systemState,
-            tokenProgram: TOKEN_PROGRAM_ID,
-            systemProgram: SystemProgram.programId,
-          } as any)
-          .signers([adminKeypair])
-          .rpc();
-        console.log("LockRecord account initialized. Transaction signature:", tx);
-
-        await provider.connection.confirmTransaction(tx, "confirmed");
-
-        const createdAccountInfo = await provider.connection.getAccountInfo(lockRecordPDA);
-        if (createdAccountInfo === null) {
-          throw new Error("Failed to create LockRecord account");
-        }
-        console.log("LockRecord account created successfully");
-
-        const lockRecordAccount = await redemptionManagerProgram.account.lockRecord.fetch(lockRecordPDA);
-        console.log("Initialized LockRecord account data:", lockRecordAccount);
-      } else {
-        console.log("LockRecord account already exists");
-      }
-    } catch (error) {
-      console.error("Error initializing LockRecord account:", error);
-      throw error;
+    const lockRecordAccount = await lockManagerProgram.account.lockRecord.fetchNullable(lockRecordPDA);
+    if (lockRecordAccount === null) {
+      throw new Error("Lock position not found; mint xxUSD through asset_manager first");
     }
+    console.log("LockRecord account data:", lockRecordAccount);
   };
 });